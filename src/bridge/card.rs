@@ -1,4 +1,8 @@
+use crate::cfr::game_model::{invalid_data, Encode, VisibleInfo};
+use crate::cfr::strategy_generation::fallback::MoveProbabilities;
 use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::io::{Read, Write};
 use std::sync::LazyLock;
 
 pub const SUITS: u8 = 4;
@@ -24,6 +28,19 @@ impl Suit {
     }
 }
 
+impl Encode for Suit {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.n().encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        ALL_SUITS
+            .get(u8::decode(r)? as usize)
+            .copied()
+            .ok_or_else(|| invalid_data("not a suit"))
+    }
+}
+
 const RANKS: u8 = 13;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -133,6 +150,19 @@ impl Card {
     }
 }
 
+impl Encode for Card {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.n.encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        match u8::decode(r)? {
+            n @ 0..52 => Ok(Self { n }),
+            _ => Err(invalid_data("not a card")),
+        }
+    }
+}
+
 impl Debug for Card {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         Display::fmt(self, f)?;
@@ -170,3 +200,16 @@ impl Display for Card {
         Ok(())
     }
 }
+
+/// A [`Heuristic`](crate::cfr::strategy_generation::fallback::Heuristic) for infosets training
+/// never reached: play the lowest legal card, so a bot at least never throws away a winner
+pub fn lowest_card<INFO: VisibleInfo<Move = Card>>(info: &INFO) -> Option<MoveProbabilities<INFO>> {
+    let mut lowest: Option<Card> = None;
+    info.run_for_moves(|c| {
+        if lowest.is_none_or(|l| c.rank().n() < l.rank().n()) {
+            lowest = Some(c);
+        }
+    });
+
+    lowest.map(|c| vec![(c, 1.0)])
+}
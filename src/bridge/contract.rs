@@ -1,4 +1,7 @@
 use crate::bridge::card::{Card, Suit};
+use crate::cfr::game_model::{invalid_data, Encode};
+use std::io;
+use std::io::{Read, Write};
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub struct Contract {
@@ -16,6 +19,46 @@ pub enum Doubling {
     Redoubled,
 }
 
+impl Encode for Contract {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.trump.encode(w)?;
+        self.n.encode(w)?;
+        self.doubling.encode(w)?;
+        self.declarer_vulnerable.encode(w)?;
+        self.defender_vulnerable.encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            trump: Encode::decode(r)?,
+            n: Encode::decode(r)?,
+            doubling: Encode::decode(r)?,
+            declarer_vulnerable: Encode::decode(r)?,
+            defender_vulnerable: Encode::decode(r)?,
+        })
+    }
+}
+
+impl Encode for Doubling {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            Doubling::None => 0u8,
+            Doubling::Doubled => 1,
+            Doubling::Redoubled => 2,
+        }
+        .encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(Doubling::None),
+            1 => Ok(Doubling::Doubled),
+            2 => Ok(Doubling::Redoubled),
+            _ => Err(invalid_data("not a doubling")),
+        }
+    }
+}
+
 impl Contract {
     pub fn card_defeats_card(&self, current_winner: Card, new_card: Card) -> bool {
         let current_is_trump = Some(current_winner.suit()) == self.trump;
@@ -22,15 +22,15 @@ pub struct BridgeGamestate {
     pub(crate) dummy_hand: Hand,
 }
 
-impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
-    fn info_for_turn_player(&self) -> VisibleInfoForBridgePlayer {
+impl BridgeGamestate {
+    fn info_for_seat(&self, seat: Seat) -> VisibleInfoForBridgePlayer {
         if self.before_declarer_hand.is_empty()
             && self.declarer_hand.is_empty()
             && self.after_declarer_hand.is_empty()
             && self.dummy_hand.is_empty()
         {
             return VisibleInfoForBridgePlayer::Terminal {
-                contract: self.contract.clone(),
+                contract: self.contract,
                 declarer_tricks: self.declarer_tricks,
             };
         }
@@ -39,7 +39,7 @@ impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
             | self.after_declarer_hand
             | self.before_declarer_hand
             | self.dummy_hand;
-        let (player_hand, other_hand) = match self.turn {
+        let (player_hand, other_hand) = match seat {
             Seat::Declarer => (self.declarer_hand, self.dummy_hand),
             Seat::AfterDeclarer => (self.after_declarer_hand, self.dummy_hand),
             Seat::Dummy => (self.dummy_hand, self.declarer_hand),
@@ -56,7 +56,7 @@ impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
         }
 
         VisibleInfoForBridgePlayer::InPlay(InfoForTurnPlayer {
-            player: self.turn,
+            player: seat,
             declarer_tricks: self.declarer_tricks,
             my_hand: player_hand.reduce(self.hand_of_cards_played),
             other_visible_hand: other_hand.reduce(self.hand_of_cards_played),
@@ -64,6 +64,16 @@ impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
             current_trick,
         })
     }
+}
+
+impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
+    fn info_for_turn_player(&self) -> VisibleInfoForBridgePlayer {
+        self.info_for_seat(self.turn)
+    }
+
+    fn info_for_player(&self, player: PlayerNumber) -> VisibleInfoForBridgePlayer {
+        self.info_for_seat(Seat::from_player_number(player))
+    }
 
     fn players_playing(&self) -> PlayerNumber {
         4
@@ -81,7 +91,7 @@ impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
     fn advance(&self, m: &Card) -> Self {
         let unreduced_card = Hand::new(&[*m]).unreduce(self.hand_of_cards_played).first();
 
-        let mut new_cards_played = self.cards_played.clone();
+        let mut new_cards_played = self.cards_played;
         new_cards_played.push(unreduced_card);
 
         let mut declarer_hand = self.declarer_hand;
@@ -89,7 +99,7 @@ impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
         let mut dummy_hand = self.dummy_hand;
         let mut before_declarer_hand = self.before_declarer_hand;
 
-        let mut turn_player_hand = match self.turn {
+        let turn_player_hand = match self.turn {
             Seat::Declarer => &mut declarer_hand,
             Seat::AfterDeclarer => &mut after_declarer_hand,
             Seat::Dummy => &mut dummy_hand,
@@ -113,7 +123,7 @@ impl OracleGamestate<VisibleInfoForBridgePlayer> for BridgeGamestate {
             before_declarer_hand,
         };
 
-        if result.cards_played.len() % 4 == 0 {
+        if result.cards_played.len().is_multiple_of(4) {
             let mut winning_card = result.cards_played[result.cards_played.len() - 4];
             let mut winning_player = result.turn;
 
@@ -4,7 +4,8 @@ use crate::bridge::gamestate::BridgeGamestate;
 use crate::bridge::hand::{Hand, FULL_HAND};
 use crate::bridge::player_info::{InfoForTurnPlayer, VisibleInfoForBridgePlayer};
 use crate::bridge::seat::Seat;
-use crate::cfr::game_model::{GamestateSampler, Probability, VisibleInfo};
+use crate::cfr::game_model::{EnumerableGamestateSampler, GamestateSampler, Probability};
+use fastrand::Rng;
 use std::mem;
 use tinyvec::ArrayVec;
 
@@ -45,24 +46,18 @@ impl GamestateSamplerForBridgePlayerInfo {
             contract,
         }
     }
-}
 
-impl GamestateSampler for GamestateSamplerForBridgePlayerInfo {
-    type Info = VisibleInfoForBridgePlayer;
+    fn unseen_cards(&self) -> Hand {
+        self.v.cards_in_other_hands.unreduce(self.played_cards)
+    }
 
-    fn sample(&mut self) -> (BridgeGamestate, Probability) {
+    fn gamestate_with_mystery_hands(
+        &self,
+        mystery_hand_one: Hand,
+        mystery_hand_two: Hand,
+    ) -> BridgeGamestate {
         let my_hand = self.v.my_hand.unreduce(self.played_cards);
         let other_hand = self.v.other_visible_hand.unreduce(self.played_cards);
-        let mut mystery_hand_one = Hand::default();
-        let mut mystery_hand_two = Hand::default();
-
-        let pick_from = self
-            .v
-            .cards_in_other_hands
-            .unreduce(self.played_cards)
-            .cards()
-            .to_vec();
-        fill_hands(pick_from, &mut mystery_hand_one, &mut mystery_hand_two);
 
         let (declarer, after_declarer, dummy, before_declarer) = match self.v.player {
             // Mystery hand one always has the same number or more cards than hand two
@@ -73,7 +68,7 @@ impl GamestateSampler for GamestateSamplerForBridgePlayerInfo {
             Seat::BeforeDeclarer => (mystery_hand_one, mystery_hand_two, other_hand, my_hand),
         };
 
-        let gamestate = BridgeGamestate {
+        BridgeGamestate {
             contract: self.contract,
             declarer_tricks: self.v.declarer_tricks,
             turn: self.v.player,
@@ -84,19 +79,89 @@ impl GamestateSampler for GamestateSamplerForBridgePlayerInfo {
 
             cards_played: self.played_cards_vec,
             hand_of_cards_played: self.played_cards,
-        };
+        }
+    }
+}
+
+impl GamestateSampler for GamestateSamplerForBridgePlayerInfo {
+    type Info = VisibleInfoForBridgePlayer;
+
+    fn sample(&mut self, rng: &mut Rng) -> (BridgeGamestate, Probability) {
+        let mut mystery_hand_one = Hand::default();
+        let mut mystery_hand_two = Hand::default();
+        fill_hands(
+            rng,
+            self.unseen_cards().cards().to_vec(),
+            &mut mystery_hand_one,
+            &mut mystery_hand_two,
+        );
 
         // FIXME: Probability is wrong
-        (gamestate, 0.001)
+        (
+            self.gamestate_with_mystery_hands(mystery_hand_one, mystery_hand_two),
+            0.001,
+        )
+    }
+}
+
+impl EnumerableGamestateSampler for GamestateSamplerForBridgePlayerInfo {
+    fn enumerate(&self) -> Vec<(BridgeGamestate, Probability)> {
+        let unseen_cards = self.unseen_cards();
+        // Mystery hand one gets the extra card when they can't be split evenly
+        let mystery_hand_one_len = unseen_cards.len().div_ceil(2) as usize;
+
+        let mut splits = Vec::new();
+        split_hands(
+            &unseen_cards.cards(),
+            mystery_hand_one_len,
+            Hand::default(),
+            unseen_cards,
+            &mut splits,
+        );
+
+        let probability = 1.0 / splits.len() as Probability;
+        splits
+            .into_iter()
+            .map(|(one, two)| (self.gamestate_with_mystery_hands(one, two), probability))
+            .collect()
+    }
+}
+
+/// Every way to pick `n` of the `cards` for the first hand, with the rest going to the second
+fn split_hands(
+    cards: &[Card],
+    n: usize,
+    first: Hand,
+    second: Hand,
+    splits: &mut Vec<(Hand, Hand)>,
+) {
+    if n == 0 {
+        splits.push((first, second));
+        return;
+    }
+
+    for (i, card) in cards.iter().enumerate().take(cards.len() + 1 - n) {
+        split_hands(
+            &cards[i + 1..],
+            n - 1,
+            first + *card,
+            second - *card,
+            splits,
+        );
     }
 }
 
-fn fill_hands(mut cards_to_pick_from: Vec<Card>, first: &mut Hand, second: &mut Hand) {
+fn fill_hands(
+    rng: &mut Rng,
+    mut cards_to_pick_from: Vec<Card>,
+    first: &mut Hand,
+    second: &mut Hand,
+) {
     let mut current_hand = first;
     let mut other_hand = second;
 
     while !cards_to_pick_from.is_empty() {
-        let i = fastrand::usize(0..cards_to_pick_from.len());
+        let i = rng.usize(0..cards_to_pick_from.len());
         let card = cards_to_pick_from.swap_remove(i);
         *current_hand += card;
 
@@ -1,10 +1,13 @@
 use crate::bridge::card::{Card, Rank, Suit, ALL_RANKS, ALL_SUITS};
+use crate::cfr::game_model::{invalid_data, Encode};
 use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{Read, Write};
 use std::ops::{Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Sub, SubAssign};
 use std::sync::LazyLock;
 use tinyvec::{array_vec, ArrayVec};
 
-#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
 pub struct Hand {
     bitset: u64,
 }
@@ -152,6 +155,21 @@ impl Hand {
     }
 }
 
+impl Encode for Hand {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.bitset.encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        let bitset = u64::decode(r)?;
+        if bitset >> 52 != 0 {
+            return Err(invalid_data("not a hand"));
+        }
+
+        Ok(Self { bitset })
+    }
+}
+
 impl Display for Hand {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.cards()))
@@ -168,7 +186,7 @@ impl Add<Card> for Hand {
     type Output = Self;
 
     fn add(self, rhs: Card) -> Self::Output {
-        let mut res = self.clone();
+        let mut res = self;
         res += rhs;
 
         res
@@ -185,7 +203,7 @@ impl Sub<Card> for Hand {
     type Output = Self;
 
     fn sub(self, rhs: Card) -> Self::Output {
-        let mut res = self.clone();
+        let mut res = self;
         res -= rhs;
 
         res
@@ -220,7 +238,7 @@ impl BitAnd<Hand> for Hand {
     type Output = Self;
 
     fn bitand(self, rhs: Hand) -> Self::Output {
-        let mut res = self.clone();
+        let mut res = self;
         res &= rhs;
 
         res
@@ -237,19 +255,13 @@ impl BitOr<Hand> for Hand {
     type Output = Self;
 
     fn bitor(self, rhs: Hand) -> Self::Output {
-        let mut res = self.clone();
+        let mut res = self;
         res |= rhs;
 
         res
     }
 }
 
-impl Default for Hand {
-    fn default() -> Self {
-        Self { bitset: 0 }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use crate::bridge::card::{Card, Rank, Suit};
@@ -6,3 +6,4 @@ pub mod hand;
 pub mod old_game;
 mod player_info;
 pub mod seat;
+pub mod trick_limit;
@@ -6,6 +6,7 @@ use crate::cfr::game_model::{
     GamestateSampler, OracleGamestate, PlayerNumber, Probability, Utility, UtilityForAllPlayers,
     VisibleInfo,
 };
+use fastrand::Rng;
 use std::hash::Hash;
 use tinyvec::ArrayVec;
 
@@ -60,7 +61,7 @@ impl BridgeGame {
         let cards_played = &self.cards_played;
         let len = cards_played.len();
 
-        if len % 4 == 0 {
+        if len.is_multiple_of(4) {
             return None;
         }
 
@@ -149,6 +150,15 @@ impl OracleGamestate<BridgeInfoSet> for BridgeGame {
         }
     }
 
+    fn info_for_player(&self, player: PlayerNumber) -> BridgeInfoSet {
+        match Seat::from_player_number(player) {
+            Seat::Declarer => BridgeInfoSet::Declarer(self.declarer_info()),
+            Seat::AfterDeclarer => BridgeInfoSet::AfterDeclarer(self.after_info()),
+            Seat::Dummy => BridgeInfoSet::Dummy(self.dummy_info()),
+            Seat::BeforeDeclarer => BridgeInfoSet::BeforeDeclarer(self.before_info()),
+        }
+    }
+
     fn players_playing(&self) -> PlayerNumber {
         4
     }
@@ -166,7 +176,7 @@ impl OracleGamestate<BridgeInfoSet> for BridgeGame {
         new.cards_played.push(*m);
         new.hand_of_cards_played += *m;
 
-        if new.cards_played.len() % 4 == 0 {
+        if new.cards_played.len().is_multiple_of(4) {
             new.handle_end_of_trick()
         } else {
             new.turn_player = self.turn_player.next();
@@ -458,10 +468,10 @@ impl BridgeGamestateSampler {
         }
     }
 
-    fn fill_hand(cards: &mut ArrayVec<[Card; 52]>, hand: &mut Hand, missing_n: u32) {
+    fn fill_hand(rng: &mut Rng, cards: &mut ArrayVec<[Card; 52]>, hand: &mut Hand, missing_n: u32) {
         let mut togo = missing_n;
         while togo > 0 {
-            let i = fastrand::usize(..cards.len());
+            let i = rng.usize(..cards.len());
             let card = cards.swap_remove(i);
             *hand += card;
             togo -= 1;
@@ -472,16 +482,17 @@ impl BridgeGamestateSampler {
 impl GamestateSampler for BridgeGamestateSampler {
     type Info = BridgeInfoSet;
 
-    fn sample(&mut self) -> (<Self::Info as VisibleInfo>::Gamestate, Probability) {
+    fn sample(&mut self, rng: &mut Rng) -> (<Self::Info as VisibleInfo>::Gamestate, Probability) {
         // FIXME: Commented out code lol
         let prob = 1.0; // / (self.combos as Probability);
         let mut cards_to_pick = self.missing_cards;
 
         let mut dummy_hand = self.dummy_hand;
-        Self::fill_hand(&mut cards_to_pick, &mut dummy_hand, self.dummy_missing);
+        Self::fill_hand(rng, &mut cards_to_pick, &mut dummy_hand, self.dummy_missing);
 
         let mut before_declarer_hand = self.before_declarer_hand;
         Self::fill_hand(
+            rng,
             &mut cards_to_pick,
             &mut before_declarer_hand,
             self.before_declarer_missing,
@@ -489,6 +500,7 @@ impl GamestateSampler for BridgeGamestateSampler {
 
         let mut declarer_hand = self.declarer_hand;
         Self::fill_hand(
+            rng,
             &mut cards_to_pick,
             &mut declarer_hand,
             self.declarer_missing,
@@ -497,6 +509,7 @@ impl GamestateSampler for BridgeGamestateSampler {
         let mut after_declarer_hand = self.after_declarer_hand;
         // TODO: Can we just += here?
         Self::fill_hand(
+            rng,
             &mut cards_to_pick,
             &mut after_declarer_hand,
             self.after_declarer_missing,
@@ -529,10 +542,13 @@ mod test {
         BridgeGamestateSampler, BridgeInfoSet, DeclarerInfoSet, UniversalInformation,
     };
     use crate::cfr::game_model::{GamestateSampler, OracleGamestate};
+    use crate::cfr::strategy_generation::strategy::Policy;
     use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
     use bumpalo_herd::Herd;
+    use fastrand::Rng;
 
     #[test]
+    #[allow(deprecated)]
     fn bridge_master_one_modified() {
         let infoset = BridgeInfoSet::Declarer(DeclarerInfoSet {
             universal_information: UniversalInformation {
@@ -597,19 +613,20 @@ mod test {
         let mut sampler = BridgeGamestateSampler::new(&infoset);
         strategy_generator.refine_strategy(sampler.clone(), 128);
 
-        let mut board = sampler.sample().0;
+        let mut rng = Rng::with_seed(strategy_generator.seed());
+        let mut board = sampler.sample(&mut rng).0;
         println!("{:?}", board);
         println!(
             "{:?}",
             strategy_generator
-                .strategy_for_info(infoset.clone())
+                .strategy_for_info(infoset.clone(), Policy::Average)
                 .move_probabilities(),
         );
         println!();
 
         while let Some(m) = strategy_generator
-            .strategy_for_info(board.info_for_turn_player().clone())
-            .pick_move()
+            .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
+            .pick_move(&mut rng)
         {
             board = board.advance(&m);
             println!("board {:?}", board);
@@ -627,13 +644,14 @@ mod test {
             println!(
                 "{:?}",
                 strategy_generator
-                    .strategy_for_info(board.info_for_turn_player().clone())
+                    .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
                     .move_probabilities(),
             );
         }
     }
 
     #[test]
+    #[allow(deprecated)]
     fn bridge_master_one_full() {
         let infoset = BridgeInfoSet::Declarer(DeclarerInfoSet {
             universal_information: UniversalInformation {
@@ -689,18 +707,19 @@ mod test {
         let mut sampler = BridgeGamestateSampler::new(&infoset);
         strategy_generator.refine_strategy(sampler.clone(), 10_000);
 
-        let mut board = sampler.sample().0;
+        let mut rng = Rng::with_seed(strategy_generator.seed());
+        let mut board = sampler.sample(&mut rng).0;
         println!(
             "{:?}",
             strategy_generator
-                .strategy_for_info(infoset.clone())
+                .strategy_for_info(infoset.clone(), Policy::Average)
                 .move_probabilities(),
         );
         println!();
 
         while let Some(m) = strategy_generator
-            .strategy_for_info(board.info_for_turn_player().clone())
-            .pick_move()
+            .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
+            .pick_move(&mut rng)
         {
             board = board.advance(&m);
             println!("board {:?}", board);
@@ -712,7 +731,7 @@ mod test {
             println!(
                 "{:?}",
                 strategy_generator
-                    .strategy_for_info(board.info_for_turn_player())
+                    .strategy_for_info(board.info_for_turn_player(), Policy::Average)
                     .move_probabilities(),
             );
         }
@@ -1,12 +1,13 @@
-use crate::bridge::card::{Card, ALL_CARDS};
+use crate::bridge::card::Card;
 use crate::bridge::contract::Contract;
 use crate::bridge::gamestate::BridgeGamestate;
-use crate::bridge::hand::{Hand, FULL_HAND};
+use crate::bridge::hand::Hand;
 use crate::bridge::seat::Seat;
 use crate::cfr::game_model::{
-    GamestateSampler, PlayerNumber, Probability, Utility, UtilityForAllPlayers, VisibleInfo,
+    invalid_data, Encode, PlayerNumber, Utility, UtilityForAllPlayers, VisibleInfo,
 };
-use std::mem;
+use std::io;
+use std::io::{Read, Write};
 use tinyvec::ArrayVec;
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -86,20 +87,137 @@ impl VisibleInfo for VisibleInfoForBridgePlayer {
     }
 }
 
+impl Encode for VisibleInfoForBridgePlayer {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            VisibleInfoForBridgePlayer::InPlay(s) => {
+                0u8.encode(w)?;
+                s.encode(w)
+            }
+            VisibleInfoForBridgePlayer::Terminal {
+                contract,
+                declarer_tricks,
+            } => {
+                1u8.encode(w)?;
+                contract.encode(w)?;
+                declarer_tricks.encode(w)
+            }
+        }
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(VisibleInfoForBridgePlayer::InPlay(Encode::decode(r)?)),
+            1 => Ok(VisibleInfoForBridgePlayer::Terminal {
+                contract: Encode::decode(r)?,
+                declarer_tricks: Encode::decode(r)?,
+            }),
+            _ => Err(invalid_data("not bridge player info")),
+        }
+    }
+}
+
+impl Encode for InfoForTurnPlayer {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.player.encode(w)?;
+        self.declarer_tricks.encode(w)?;
+        self.my_hand.encode(w)?;
+        self.other_visible_hand.encode(w)?;
+        self.cards_in_other_hands.encode(w)?;
+
+        (self.current_trick.len() as u8).encode(w)?;
+        self.current_trick.iter().try_for_each(|c| c.encode(w))
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        let player = Encode::decode(r)?;
+        let declarer_tricks = Encode::decode(r)?;
+        let my_hand = Encode::decode(r)?;
+        let other_visible_hand = Encode::decode(r)?;
+        let cards_in_other_hands = Encode::decode(r)?;
+
+        let mut current_trick = ArrayVec::new();
+        for _ in 0..u8::decode(r)? {
+            current_trick
+                .try_push(Card::decode(r)?)
+                .map_or(Ok(()), |_| Err(invalid_data("trick has too many cards")))?;
+        }
+
+        Ok(Self {
+            player,
+            declarer_tricks,
+            my_hand,
+            other_visible_hand,
+            cards_in_other_hands,
+            current_trick,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::bridge::card::{Card, Rank, Suit};
+    use crate::bridge::card::{lowest_card, Card, Rank, Suit};
     use crate::bridge::contract::{Contract, Doubling};
     use crate::bridge::gamestate_sampler::GamestateSamplerForBridgePlayerInfo;
     use crate::bridge::hand::{Hand, FULL_HAND};
-    use crate::bridge::player_info::InfoForTurnPlayer;
+    use crate::bridge::player_info::{InfoForTurnPlayer, VisibleInfoForBridgePlayer};
     use crate::bridge::seat::Seat;
     use crate::cfr::game_model::{GamestateSampler, OracleGamestate};
+    use crate::cfr::strategy_generation::fallback::Heuristic;
+    use crate::cfr::strategy_generation::strategy::{Policy, StrategySource};
     use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
     use bumpalo_herd::Herd;
+    use fastrand::Rng;
     use tinyvec::array_vec;
 
+    fn info_following_hearts() -> VisibleInfoForBridgePlayer {
+        VisibleInfoForBridgePlayer::InPlay(InfoForTurnPlayer {
+            player: Seat::AfterDeclarer,
+            declarer_tricks: 0,
+            my_hand: Hand::new(&[
+                Card::new(Suit::Hearts, Rank::Ace),
+                Card::new(Suit::Hearts, Rank::Four),
+                Card::new(Suit::Spades, Rank::Two),
+            ]),
+            other_visible_hand: Hand::default(),
+            cards_in_other_hands: Hand::default(),
+            current_trick: array_vec!([Card; 4] => Card::new(Suit::Hearts, Rank::Ten)),
+        })
+    }
+
+    #[test]
+    fn falling_back_to_the_lowest_card_follows_suit() {
+        assert_eq!(
+            lowest_card(&info_following_hearts()),
+            Some(vec![(Card::new(Suit::Hearts, Rank::Four), 1.0)])
+        );
+    }
+
+    #[test]
+    fn untrained_bots_fall_back_to_the_lowest_card() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd).with_fallback(Heuristic {
+            heuristic: lowest_card,
+        });
+
+        let info = info_following_hearts();
+        let strategy = strategy_generator.strategy_for_info_or_fallback(
+            &info,
+            Policy::Average,
+            &mut Rng::with_seed(0),
+        );
+        assert_eq!(strategy.source(), StrategySource::Heuristic);
+        assert_eq!(
+            strategy.move_probability(&Card::new(Suit::Hearts, Rank::Four)),
+            1.0
+        );
+        assert!(strategy_generator
+            .lookup_strategy_for_info(&info, Policy::Average)
+            .is_none());
+    }
+
     #[test]
+    #[allow(deprecated)]
     fn bridge_master_one_modified() {
         let herd = Herd::new();
         let strategy_generator = StrategyGenerator::new(&herd);
@@ -166,19 +284,20 @@ mod test {
             GamestateSamplerForBridgePlayerInfo::new(info_for_turn_player, contract, played_cards);
         strategy_generator.refine_strategy(sampler.clone(), 128);
 
-        let mut board = sampler.sample().0;
+        let mut rng = Rng::with_seed(strategy_generator.seed());
+        let mut board = sampler.sample(&mut rng).0;
         println!("{:?}", board);
         println!(
             "{:?}",
             strategy_generator
-                .strategy_for_info(board.info_for_turn_player().clone())
+                .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
                 .move_probabilities(),
         );
         println!();
 
         while let Some(m) = strategy_generator
-            .strategy_for_info(board.info_for_turn_player().clone())
-            .pick_move()
+            .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
+            .pick_move(&mut rng)
         {
             board = board.advance(&m);
             println!("board {:?}", board);
@@ -193,13 +312,14 @@ mod test {
             println!(
                 "{:?}",
                 strategy_generator
-                    .strategy_for_info(board.info_for_turn_player().clone())
+                    .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
                     .move_probabilities(),
             );
         }
     }
 
     #[test]
+    #[allow(deprecated)]
     fn bridge_master_expert_a1() {
         let herd = Herd::new();
         let strategy_generator = StrategyGenerator::new(&herd);
@@ -263,19 +383,20 @@ mod test {
             GamestateSamplerForBridgePlayerInfo::new(info_for_turn_player, contract, played_cards);
         strategy_generator.refine_strategy(sampler.clone(), 128);
 
-        let mut board = sampler.sample().0;
+        let mut rng = Rng::with_seed(strategy_generator.seed());
+        let mut board = sampler.sample(&mut rng).0;
         println!("{:?}", board);
         println!(
             "{:?}",
             strategy_generator
-                .strategy_for_info(board.info_for_turn_player().clone())
+                .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
                 .move_probabilities(),
         );
         println!();
 
         while let Some(m) = strategy_generator
-            .strategy_for_info(board.info_for_turn_player().clone())
-            .pick_move()
+            .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
+            .pick_move(&mut rng)
         {
             board = board.advance(&m);
             println!("board {:?}", board);
@@ -290,7 +411,7 @@ mod test {
             println!(
                 "{:?}",
                 strategy_generator
-                    .strategy_for_info(board.info_for_turn_player().clone())
+                    .strategy_for_info(board.info_for_turn_player().clone(), Policy::Average)
                     .move_probabilities(),
             );
         }
@@ -1,18 +1,37 @@
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+use crate::cfr::game_model::{invalid_data, Encode, PlayerNumber};
+use crate::cfr::strategy_generation::traverser_schedule::{Team, Traversers};
+use std::io;
+use std::io::{Read, Write};
+
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum Seat {
+    #[default]
     Declarer,
     AfterDeclarer,
     Dummy,
     BeforeDeclarer,
 }
 
-impl Default for Seat {
-    fn default() -> Self {
-        Self::Declarer
+impl Seat {
+    pub fn from_player_number(player: PlayerNumber) -> Self {
+        match player {
+            0 => Seat::Declarer,
+            1 => Seat::AfterDeclarer,
+            2 => Seat::Dummy,
+            3 => Seat::BeforeDeclarer,
+            _ => panic!("Bridge only has four players"),
+        }
+    }
+
+    /// Traverses declarer with dummy, and the defenders with each other, since partners share
+    /// a utility
+    pub fn partnership_schedule() -> Team {
+        Team::new(vec![
+            Traversers::single(0).with(2),
+            Traversers::single(1).with(3),
+        ])
     }
-}
 
-impl Seat {
     pub fn next(&self) -> Self {
         match self {
             Seat::Declarer => Self::AfterDeclarer,
@@ -31,3 +50,41 @@ impl Seat {
         }
     }
 }
+
+impl Encode for Seat {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            Seat::Declarer => 0u8,
+            Seat::AfterDeclarer => 1,
+            Seat::Dummy => 2,
+            Seat::BeforeDeclarer => 3,
+        }
+        .encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        match u8::decode(r)? {
+            n @ 0..4 => Ok(Seat::from_player_number(n as PlayerNumber)),
+            _ => Err(invalid_data("not a seat")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bridge::seat::Seat;
+    use crate::cfr::strategy_generation::traverser_schedule::TraverserSchedule;
+
+    #[test]
+    fn partners_are_traversed_together() {
+        let schedule = Seat::partnership_schedule();
+
+        for iteration in 0..4 {
+            let traversers = schedule.traversers(iteration, 0, 4);
+            assert_eq!(traversers.contains(0), traversers.contains(2));
+            assert_eq!(traversers.contains(1), traversers.contains(3));
+            assert_ne!(traversers.contains(0), traversers.contains(1));
+            assert_ne!(traversers, schedule.traversers(iteration + 1, 0, 4));
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::bridge::gamestate::BridgeGamestate;
+use crate::bridge::player_info::VisibleInfoForBridgePlayer;
+use crate::cfr::strategy_generation::depth_limit::LeafCondition;
+
+/// Stops the traversal at the end of a trick, once this many have been completed
+///
+/// A trick that was already underway when the traversal started counts once it's completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrickLimit {
+    pub tricks: u32,
+}
+
+impl LeafCondition<VisibleInfoForBridgePlayer> for TrickLimit {
+    fn is_leaf(&self, gamestate: &BridgeGamestate, depth: u32) -> bool {
+        let cards_played = gamestate.cards_played.len() as u32;
+        if !cards_played.is_multiple_of(4) {
+            return false;
+        }
+
+        let tricks_completed = cards_played / 4 - (cards_played - depth) / 4;
+        tricks_completed >= self.tricks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bridge::card::{Card, Rank, Suit};
+    use crate::bridge::contract::{Contract, Doubling};
+    use crate::bridge::gamestate::BridgeGamestate;
+    use crate::bridge::hand::Hand;
+    use crate::bridge::player_info::VisibleInfoForBridgePlayer;
+    use crate::bridge::seat::Seat;
+    use crate::bridge::trick_limit::TrickLimit;
+    use crate::cfr::game_model::{GamestateSampler, OracleGamestate, Probability};
+    use crate::cfr::strategy_generation::depth_limit::{DepthLimit, PerfectInformationRollout};
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::Traversal;
+    use bumpalo_herd::Herd;
+    use fastrand::Rng;
+    use tinyvec::ArrayVec;
+
+    /// Always the same gamestate, so the traversal only covers the tree below it
+    #[derive(Clone)]
+    struct Fixed(BridgeGamestate);
+
+    impl GamestateSampler for Fixed {
+        type Info = VisibleInfoForBridgePlayer;
+
+        fn sample(&mut self, _rng: &mut Rng) -> (BridgeGamestate, Probability) {
+            (self.0.clone(), 1.0)
+        }
+    }
+
+    #[test]
+    fn traversals_stop_at_the_end_of_the_trick() {
+        // The last two tricks, with the first card of the first one already led
+        let hearts = |ranks: &[Rank]| {
+            Hand::new(
+                &ranks
+                    .iter()
+                    .map(|rank| Card::new(Suit::Hearts, *rank))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let gamestate = BridgeGamestate {
+            contract: Contract {
+                trump: None,
+                n: 1,
+                doubling: Doubling::None,
+                declarer_vulnerable: false,
+                defender_vulnerable: false,
+            },
+            declarer_tricks: 0,
+            turn: Seat::AfterDeclarer,
+            cards_played: ArrayVec::new(),
+            hand_of_cards_played: Hand::default(),
+            before_declarer_hand: hearts(&[Rank::Queen, Rank::Four]),
+            declarer_hand: hearts(&[Rank::Jack, Rank::Five]),
+            after_declarer_hand: hearts(&[Rank::Ace, Rank::Two]),
+            dummy_hand: hearts(&[Rank::King, Rank::Three]),
+        }
+        .advance(&Card::new(Suit::Hearts, Rank::Ace));
+
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_batch_size(1)
+            .with_traversal(Traversal::Full)
+            .with_depth_limit(DepthLimit::new(
+                TrickLimit { tricks: 1 },
+                PerfectInformationRollout::default(),
+            ));
+        strategy_generator.refine_strategy(Fixed(gamestate), 1);
+
+        // Every card left in the trick gets an infoset, and nothing after it does
+        let mut trick_sizes = Vec::new();
+        strategy_generator
+            .into_strategy()
+            .infosets
+            .for_each(|info, _| match info {
+                VisibleInfoForBridgePlayer::InPlay(info) => {
+                    trick_sizes.push(info.current_trick.len())
+                }
+                VisibleInfoForBridgePlayer::Terminal { .. } => trick_sizes.push(0),
+            });
+        trick_sizes.sort();
+        trick_sizes.dedup();
+        assert_eq!(trick_sizes, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::cfr::gadget::{Gadget, GadgetDescription, GadgetInfo, GadgetInfoNode, GadgetMove};
+use crate::cfr::game_model::{OracleGamestate, PlayerNumber, Probability, VisibleInfo};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub(crate) enum GadgetNode<GAMESTATE> {
+    Start,
+    /// Max-margin only: the opponent has picked an infoset, and chance picks a world in it
+    EnteredRoot {
+        root: usize,
+    },
+    /// Resolve only: the opponent decides whether to enter the subgame
+    Choice {
+        world: usize,
+    },
+    TookAlternative {
+        root: usize,
+    },
+    Subgame {
+        gamestate: GAMESTATE,
+        root: usize,
+    },
+}
+
+/// A gamestate in a gadget game, see [`Gadget`]
+pub struct GadgetGamestate<INFO: VisibleInfo> {
+    pub(crate) description: Arc<GadgetDescription<INFO>>,
+    pub(crate) node: GadgetNode<INFO::Gamestate>,
+}
+
+impl<INFO: VisibleInfo> GadgetGamestate<INFO> {
+    fn with_node(&self, node: GadgetNode<INFO::Gamestate>) -> Self {
+        Self {
+            description: self.description.clone(),
+            node,
+        }
+    }
+
+    fn with_info_node(&self, node: GadgetInfoNode<INFO>) -> GadgetInfo<INFO> {
+        GadgetInfo {
+            description: self.description.clone(),
+            node,
+        }
+    }
+
+    fn subgame_info(&self, info: INFO, root: usize) -> GadgetInfo<INFO> {
+        // Terminal utility can depend on the root, but nothing anyone decides on can
+        let node = if info.run_for_moves(|_| {}).is_some() {
+            GadgetInfoNode::SubgameOver { info, root }
+        } else {
+            GadgetInfoNode::Subgame(info)
+        };
+
+        self.with_info_node(node)
+    }
+}
+
+impl<INFO: VisibleInfo> OracleGamestate<GadgetInfo<INFO>> for GadgetGamestate<INFO> {
+    fn info_for_turn_player(&self) -> GadgetInfo<INFO> {
+        match &self.node {
+            GadgetNode::Start => self.with_info_node(GadgetInfoNode::EnterRoot),
+            GadgetNode::Choice { world } => self.with_info_node(GadgetInfoNode::Choice {
+                root: self.description.worlds[*world].root,
+            }),
+            GadgetNode::TookAlternative { root } => {
+                self.with_info_node(GadgetInfoNode::TookAlternative { root: *root })
+            }
+            GadgetNode::Subgame { gamestate, root } => {
+                self.subgame_info(gamestate.info_for_turn_player(), *root)
+            }
+            GadgetNode::EnteredRoot { .. } => unreachable!("Chance acts here"),
+        }
+    }
+
+    fn info_for_player(&self, player: PlayerNumber) -> GadgetInfo<INFO> {
+        match &self.node {
+            GadgetNode::Subgame { gamestate, root } => {
+                self.subgame_info(gamestate.info_for_player(player), *root)
+            }
+            _ => self.info_for_turn_player(),
+        }
+    }
+
+    fn players_playing(&self) -> PlayerNumber {
+        self.description.players_playing
+    }
+
+    fn turn(&self) -> PlayerNumber {
+        match &self.node {
+            GadgetNode::Subgame { gamestate, .. } => gamestate.turn(),
+            _ => self.description.opponent,
+        }
+    }
+
+    fn advance(&self, m: &GadgetMove<INFO::Move>) -> Self {
+        let node = match (&self.node, m) {
+            (GadgetNode::Start, GadgetMove::EnterRoot(root)) => {
+                GadgetNode::EnteredRoot { root: *root }
+            }
+            (GadgetNode::Choice { world }, GadgetMove::TakeAlternative) => {
+                GadgetNode::TookAlternative {
+                    root: self.description.worlds[*world].root,
+                }
+            }
+            (GadgetNode::Choice { world }, GadgetMove::Enter) => {
+                let world = &self.description.worlds[*world];
+                GadgetNode::Subgame {
+                    gamestate: world.gamestate.clone(),
+                    root: world.root,
+                }
+            }
+            (GadgetNode::Subgame { gamestate, root }, GadgetMove::Play(m)) => GadgetNode::Subgame {
+                gamestate: gamestate.advance(m),
+                root: *root,
+            },
+            (node, m) => panic!("Can't play {:?} at {:?}", m, node),
+        };
+
+        self.with_node(node)
+    }
+
+    fn chance_outcomes(&self) -> Option<Vec<(Self, Probability)>> {
+        let worlds = &self.description.worlds;
+
+        match &self.node {
+            GadgetNode::Start if self.description.gadget == Gadget::Resolve => {
+                let total: Probability = worlds.iter().map(|w| w.probability).sum();
+                Some(
+                    (0..worlds.len())
+                        .map(|world| {
+                            (
+                                self.with_node(GadgetNode::Choice { world }),
+                                worlds[world].probability / total,
+                            )
+                        })
+                        .collect(),
+                )
+            }
+            GadgetNode::EnteredRoot { root } => {
+                let total = self.description.roots[*root].probability;
+                Some(
+                    worlds
+                        .iter()
+                        .filter(|w| w.root == *root)
+                        .map(|w| {
+                            (
+                                self.with_node(GadgetNode::Subgame {
+                                    gamestate: w.gamestate.clone(),
+                                    root: *root,
+                                }),
+                                w.probability / total,
+                            )
+                        })
+                        .collect(),
+                )
+            }
+            GadgetNode::Subgame { gamestate, root } => {
+                gamestate.chance_outcomes().map(|outcomes| {
+                    outcomes
+                        .into_iter()
+                        .map(|(gamestate, p)| {
+                            (
+                                self.with_node(GadgetNode::Subgame {
+                                    gamestate,
+                                    root: *root,
+                                }),
+                                p,
+                            )
+                        })
+                        .collect()
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<INFO: VisibleInfo> Clone for GadgetGamestate<INFO> {
+    fn clone(&self) -> Self {
+        self.with_node(self.node.clone())
+    }
+}
+
+impl<INFO: VisibleInfo> Hash for GadgetGamestate<INFO> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state)
+    }
+}
+
+impl<INFO: VisibleInfo> PartialEq for GadgetGamestate<INFO> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<INFO: VisibleInfo> Eq for GadgetGamestate<INFO> {}
+
+impl<INFO: VisibleInfo> Debug for GadgetGamestate<INFO> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.node.fmt(f)
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cfr::gadget::{Gadget, GadgetDescription, GadgetGamestate};
+use crate::cfr::game_model::{PlayerNumber, UtilityForAllPlayers, VisibleInfo};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// A move in a gadget game
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum GadgetMove<MOVE> {
+    /// Max-margin only: the opponent enters the subgame at this infoset of theirs
+    EnterRoot(usize),
+    /// Resolve only: the opponent takes their alternative instead of entering the subgame
+    TakeAlternative,
+    /// Resolve only: the opponent enters the subgame
+    Enter,
+    /// A move from the subgame itself
+    Play(MOVE),
+}
+
+#[derive(Debug, Hash, PartialEq, Eq)]
+pub(crate) enum GadgetInfoNode<INFO> {
+    EnterRoot,
+    Choice { root: usize },
+    TookAlternative { root: usize },
+    Subgame(INFO),
+    SubgameOver { info: INFO, root: usize },
+}
+
+/// An infoset in a gadget game, see [`Gadget`]
+pub struct GadgetInfo<INFO: VisibleInfo> {
+    pub(crate) description: Arc<GadgetDescription<INFO>>,
+    pub(crate) node: GadgetInfoNode<INFO>,
+}
+
+impl<INFO: VisibleInfo> VisibleInfo for GadgetInfo<INFO> {
+    type Move = GadgetMove<INFO::Move>;
+    type Gamestate = GadgetGamestate<INFO>;
+
+    fn players_playing(&self) -> PlayerNumber {
+        self.description.players_playing
+    }
+
+    fn turn(&self) -> PlayerNumber {
+        match &self.node {
+            GadgetInfoNode::Subgame(info) | GadgetInfoNode::SubgameOver { info, .. } => info.turn(),
+            _ => self.description.opponent,
+        }
+    }
+
+    fn run_for_moves(&self, mut f: impl FnMut(Self::Move)) -> Option<UtilityForAllPlayers> {
+        match &self.node {
+            GadgetInfoNode::EnterRoot => {
+                for root in 0..self.description.roots.len() {
+                    f(GadgetMove::EnterRoot(root));
+                }
+                None
+            }
+            GadgetInfoNode::Choice { .. } => {
+                f(GadgetMove::TakeAlternative);
+                f(GadgetMove::Enter);
+                None
+            }
+            GadgetInfoNode::TookAlternative { root } => {
+                Some(self.description.roots[*root].alternative)
+            }
+            GadgetInfoNode::Subgame(info) => info.run_for_moves(|m| f(GadgetMove::Play(m))),
+            GadgetInfoNode::SubgameOver { info, root } => {
+                let mut utility = info
+                    .run_for_moves(|_| {})
+                    .expect("The subgame is over, so this is terminal");
+
+                if self.description.gadget == Gadget::MaxMargin {
+                    utility.accumulate(&self.description.roots[*root].alternative, -1.0);
+                }
+
+                Some(utility)
+            }
+        }
+    }
+}
+
+impl<INFO: VisibleInfo> Hash for GadgetInfo<INFO> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state)
+    }
+}
+
+impl<INFO: VisibleInfo> PartialEq for GadgetInfo<INFO> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<INFO: VisibleInfo> Eq for GadgetInfo<INFO> {}
+
+impl<INFO: VisibleInfo> Debug for GadgetInfo<INFO> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.node.fmt(f)
+    }
+}
@@ -0,0 +1,388 @@
+mod gamestate;
+mod info;
+
+pub use gamestate::*;
+pub use info::*;
+
+use crate::cfr::game_model::{
+    EnumerableGamestateSampler, GamestateSampler, OracleGamestate, PlayerNumber, Probability,
+    UtilityForAllPlayers, VisibleInfo,
+};
+use fastrand::Rng;
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Which gadget game to use when safely re-solving a subgame
+///
+/// Both put a decision for the opponent in front of the subgame, which lets them fall back to
+/// what they could get against the blueprint. Solving the gadget game to convergence then gives a
+/// strategy for the subgame that's no more exploitable than the blueprint.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Gadget {
+    /// At each of their infosets at the root, the opponent picks between entering the subgame
+    /// and taking their alternative. Re-solving only has to keep entering from being better
+    #[default]
+    Resolve,
+    /// The opponent picks which of their infosets to enter at, and is paid the margin over their
+    /// alternative. Re-solving makes the smallest margin as large as possible, so it also
+    /// improves on the blueprint where it can
+    MaxMargin,
+}
+
+#[derive(Debug)]
+struct GadgetWorld<INFO: VisibleInfo> {
+    gamestate: INFO::Gamestate,
+    probability: Probability,
+    root: usize,
+}
+
+/// One of the opponent's infosets at the root of the subgame
+#[derive(Debug)]
+struct GadgetRoot {
+    alternative: UtilityForAllPlayers,
+    probability: Probability,
+}
+
+/// Everything about a gadget game that doesn't change as it's played
+#[derive(Debug)]
+pub(crate) struct GadgetDescription<INFO: VisibleInfo> {
+    gadget: Gadget,
+    opponent: PlayerNumber,
+    players_playing: PlayerNumber,
+
+    worlds: Vec<GadgetWorld<INFO>>,
+    roots: Vec<GadgetRoot>,
+}
+
+/// Starts a gadget game for safely re-solving a subgame, see [`Gadget`]
+#[derive(Debug)]
+pub struct GadgetSampler<INFO: VisibleInfo> {
+    description: Arc<GadgetDescription<INFO>>,
+}
+
+impl<INFO: VisibleInfo> GadgetSampler<INFO> {
+    /// `worlds` are the gamestates the subgame can start from, each with how likely chance and
+    /// everyone but `opponent` were to reach it. `alternatives` are what each world is worth to
+    /// every player if the opponent doesn't enter the subgame, usually from best responding to
+    /// the blueprint
+    pub fn new(
+        gadget: Gadget,
+        opponent: PlayerNumber,
+        worlds: Vec<(INFO::Gamestate, Probability)>,
+        alternatives: Vec<UtilityForAllPlayers>,
+    ) -> Self {
+        assert_eq!(worlds.len(), alternatives.len());
+        assert!(!worlds.is_empty(), "A subgame needs a world to start from");
+
+        let players_playing = worlds[0].0.players_playing();
+
+        let mut root_for_info: FxHashMap<INFO, usize> = FxHashMap::default();
+        let mut roots: Vec<GadgetRoot> = Vec::new();
+        let mut gadget_worlds = Vec::new();
+        for ((gamestate, probability), alternative) in worlds.into_iter().zip(alternatives) {
+            let next_root = roots.len();
+            let root = *root_for_info
+                .entry(gamestate.info_for_player(opponent))
+                .or_insert(next_root);
+            if root == next_root {
+                roots.push(GadgetRoot {
+                    alternative: UtilityForAllPlayers::default(),
+                    probability: 0.0,
+                });
+            }
+
+            // The alternative for an infoset is the average over its worlds, weighted by reach
+            roots[root]
+                .alternative
+                .accumulate(&alternative, probability);
+            roots[root].probability += probability;
+
+            gadget_worlds.push(GadgetWorld {
+                gamestate,
+                probability,
+                root,
+            });
+        }
+
+        for root in &mut roots {
+            if root.probability > 0.0 {
+                root.alternative.reduce(1.0 / root.probability);
+            }
+        }
+
+        Self {
+            description: Arc::new(GadgetDescription {
+                gadget,
+                opponent,
+                players_playing,
+                worlds: gadget_worlds,
+                roots,
+            }),
+        }
+    }
+
+    /// Wraps an infoset from inside the subgame, to look up its strategy in the gadget game
+    pub fn info(&self, info: INFO) -> GadgetInfo<INFO> {
+        GadgetInfo {
+            description: self.description.clone(),
+            node: GadgetInfoNode::Subgame(info),
+        }
+    }
+
+    fn start(&self) -> GadgetGamestate<INFO> {
+        GadgetGamestate {
+            description: self.description.clone(),
+            node: GadgetNode::Start,
+        }
+    }
+}
+
+impl<INFO: VisibleInfo> Clone for GadgetSampler<INFO> {
+    fn clone(&self) -> Self {
+        Self {
+            description: self.description.clone(),
+        }
+    }
+}
+
+impl<INFO: VisibleInfo> GamestateSampler for GadgetSampler<INFO> {
+    type Info = GadgetInfo<INFO>;
+
+    fn sample(&mut self, _rng: &mut Rng) -> (GadgetGamestate<INFO>, Probability) {
+        (self.start(), 1.0)
+    }
+}
+
+impl<INFO: VisibleInfo> EnumerableGamestateSampler for GadgetSampler<INFO> {
+    fn enumerate(&self) -> Vec<(GadgetGamestate<INFO>, Probability)> {
+        vec![(self.start(), 1.0)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::gadget::{Gadget, GadgetMove};
+    use crate::cfr::game_model::{
+        EnumerableGamestateSampler, GamestateSampler, OracleGamestate, PlayerNumber, Probability,
+        UtilityForAllPlayers, VisibleInfo,
+    };
+    use crate::cfr::strategy_evaluation::{best_response_values, evaluate_strategy};
+    use crate::cfr::strategy_generation::cfr_config::DiscountedCfr;
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::{board_with_win_for_x, WINNING_MOVE};
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+    use fastrand::Rng;
+
+    /// Player 1 is dealt a high or a low card, and player 0 has to guess which without seeing it,
+    /// or pass. Player 1 wins 2 if their high card is guessed wrong and 1 if their low card is,
+    /// loses 1 if either is guessed right, and loses a quarter if player 0 passes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum HiddenCardGame {
+        Dealt { high: bool },
+        Guessed { high: bool, guess: Option<bool> },
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum HiddenCardInfo {
+        Guess,
+        Holds { high: bool },
+        Guessed { high: bool, guess: Option<bool> },
+    }
+
+    impl OracleGamestate<HiddenCardInfo> for HiddenCardGame {
+        fn info_for_turn_player(&self) -> HiddenCardInfo {
+            self.info_for_player(0)
+        }
+
+        fn info_for_player(&self, player: PlayerNumber) -> HiddenCardInfo {
+            match *self {
+                HiddenCardGame::Dealt { high } if player == 1 => HiddenCardInfo::Holds { high },
+                HiddenCardGame::Dealt { .. } => HiddenCardInfo::Guess,
+                HiddenCardGame::Guessed { high, guess } => HiddenCardInfo::Guessed { high, guess },
+            }
+        }
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn advance(&self, guess: &Option<bool>) -> Self {
+            match *self {
+                HiddenCardGame::Dealt { high } => HiddenCardGame::Guessed {
+                    high,
+                    guess: *guess,
+                },
+                HiddenCardGame::Guessed { .. } => unreachable!(),
+            }
+        }
+    }
+
+    impl VisibleInfo for HiddenCardInfo {
+        type Move = Option<bool>;
+        type Gamestate = HiddenCardGame;
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn run_for_moves(&self, mut f: impl FnMut(Option<bool>)) -> Option<UtilityForAllPlayers> {
+            match *self {
+                HiddenCardInfo::Guessed { high, guess } => {
+                    let won = match guess {
+                        None => -0.25,
+                        Some(guess) if guess == high => -1.0,
+                        Some(_) if high => 2.0,
+                        Some(_) => 1.0,
+                    };
+                    Some(UtilityForAllPlayers {
+                        util: [-won, won, 0.0, 0.0],
+                    })
+                }
+                _ => {
+                    f(Some(true));
+                    f(Some(false));
+                    f(None);
+                    None
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct HiddenCardSampler;
+
+    impl GamestateSampler for HiddenCardSampler {
+        type Info = HiddenCardInfo;
+
+        fn sample(&mut self, rng: &mut Rng) -> (HiddenCardGame, Probability) {
+            (HiddenCardGame::Dealt { high: rng.bool() }, 0.5)
+        }
+    }
+
+    impl EnumerableGamestateSampler for HiddenCardSampler {
+        fn enumerate(&self) -> Vec<(HiddenCardGame, Probability)> {
+            vec![
+                (HiddenCardGame::Dealt { high: true }, 0.5),
+                (HiddenCardGame::Dealt { high: false }, 0.5),
+            ]
+        }
+    }
+
+    #[test]
+    fn resolving_with_a_gadget_takes_the_win() {
+        let herd = Herd::new();
+        let blueprint = StrategyGenerator::with_config(&herd, DiscountedCfr::default());
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        blueprint.refine_strategy(sampler.clone(), 20);
+
+        for gadget in [Gadget::Resolve, Gadget::MaxMargin] {
+            let gadget_sampler = blueprint.subgame_gadget(&sampler, 1, gadget, Policy::Average);
+            let resolver = StrategyGenerator::with_config(&herd, DiscountedCfr::default());
+            resolver.refine_strategy(gadget_sampler.clone(), 200);
+
+            let winning_move = GadgetMove::Play(WINNING_MOVE);
+            let average = resolver
+                .lookup_strategy_for_info(
+                    &gadget_sampler.info(board_with_win_for_x()),
+                    Policy::Average,
+                )
+                .unwrap();
+            assert!(average.move_probability(&winning_move) > 0.9);
+        }
+    }
+
+    #[test]
+    fn resolving_with_a_gadget_is_no_more_exploitable() {
+        let herd = Herd::new();
+        let blueprint = StrategyGenerator::with_config(&herd, DiscountedCfr::default());
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        blueprint.refine_strategy(sampler.clone(), 20);
+        let before = blueprint.exploitability(&sampler, Policy::Average);
+
+        for gadget in [Gadget::Resolve, Gadget::MaxMargin] {
+            let gadget_sampler = blueprint.subgame_gadget(&sampler, 1, gadget, Policy::Average);
+            let resolver = StrategyGenerator::with_config(&herd, DiscountedCfr::default());
+            resolver.refine_strategy(gadget_sampler.clone(), 200);
+
+            // The subgame is the whole game here, so the re-solved strategy is played everywhere
+            let after = evaluate_strategy(&sampler, |info| {
+                resolver
+                    .lookup_strategy_for_info(&gadget_sampler.info(info), Policy::Average)
+                    .unwrap()
+                    .iter()
+                    .filter_map(|(m, p)| match m {
+                        GadgetMove::Play(m) => Some((*m, *p)),
+                        _ => None,
+                    })
+                    .collect()
+            });
+
+            // Safe re-solving: the opponent can't get more against the re-solved strategy than
+            // against the blueprint
+            assert!(after.best_response_value.get(1) <= before.best_response_value.get(1));
+            assert!(after.nash_conv() <= before.nash_conv());
+        }
+    }
+
+    #[test]
+    fn resolving_with_a_gadget_keeps_every_opponent_infoset_safe() {
+        let herd = Herd::new();
+        // Few iterations, so the blueprint still has plenty of room to improve on
+        let blueprint = StrategyGenerator::with_config(&herd, DiscountedCfr::default());
+        blueprint.refine_strategy(HiddenCardSampler, 3);
+
+        // Each world is its own infoset for the opponent, so it's also its own root
+        let worlds = HiddenCardSampler.enumerate();
+        let alternatives = best_response_values(&worlds, 1, |info| {
+            blueprint
+                .lookup_strategy_for_info(&info, Policy::Average)
+                .unwrap()
+                .iter()
+                .map(|(m, p)| (*m, *p))
+                .collect()
+        });
+
+        for gadget in [Gadget::Resolve, Gadget::MaxMargin] {
+            let gadget_sampler =
+                blueprint.subgame_gadget(&HiddenCardSampler, 1, gadget, Policy::Average);
+            let resolver = StrategyGenerator::with_config(&herd, DiscountedCfr::default());
+            resolver.refine_strategy(gadget_sampler.clone(), 1000);
+
+            let resolved = best_response_values(&worlds, 1, |info| {
+                resolver
+                    .lookup_strategy_for_info(&gadget_sampler.info(info), Policy::Average)
+                    .unwrap()
+                    .iter()
+                    .filter_map(|(m, p)| match m {
+                        GadgetMove::Play(m) => Some((*m, *p)),
+                        _ => None,
+                    })
+                    .collect()
+            });
+
+            for (resolved, alternative) in resolved.iter().zip(&alternatives) {
+                assert!(
+                    resolved.get(1) <= alternative.get(1),
+                    "{:?}: {:?} is worth more to the opponent than {:?}",
+                    gadget,
+                    resolved,
+                    alternative
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::io;
+use std::io::{Read, Write};
+
+/// A compact binary encoding, for types that need to outlive the process, such as the infosets
+/// and moves in a checkpoint
+///
+/// Numbers are little endian. Decoding whatever was encoded has to give back an equal value.
+pub trait Encode: Sized {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()>;
+
+    fn decode(r: &mut impl Read) -> io::Result<Self>;
+}
+
+/// The error for input that can't have come from [`Encode::encode`]
+pub fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+macro_rules! encode_number {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+
+                fn decode(r: &mut impl Read) -> io::Result<Self> {
+                    let mut bytes = [0; size_of::<$t>()];
+                    r.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+encode_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl Encode for usize {
+    /// Always 64 bits, so checkpoints move between platforms
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        (*self as u64).encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        usize::try_from(u64::decode(r)?).map_err(|_| invalid_data("usize out of range"))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        (*self as u8).encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("bool out of range")),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            None => false.encode(w),
+            Some(x) => {
+                true.encode(w)?;
+                x.encode(w)
+            }
+        }
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        Ok(match bool::decode(r)? {
+            false => None,
+            true => Some(T::decode(r)?),
+        })
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.iter().try_for_each(|x| x.encode(w))
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        let decoded: Vec<T> = (0..N).map(|_| T::decode(r)).collect::<io::Result<_>>()?;
+
+        Ok(decoded
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("decoded exactly N elements")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::game_model::Encode;
+    use std::fmt::Debug;
+
+    fn round_trip<T: Encode + PartialEq + Debug>(x: T) {
+        let mut bytes = Vec::new();
+        x.encode(&mut bytes).unwrap();
+
+        let mut r = bytes.as_slice();
+        assert_eq!(T::decode(&mut r).unwrap(), x);
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        round_trip(0x1234_5678u32);
+        round_trip(-3i32);
+        round_trip(0.1f64);
+        round_trip(usize::MAX);
+        round_trip(Some(true));
+        round_trip(None::<u8>);
+        round_trip([Some(1u16), None, Some(3)]);
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let mut bytes = Vec::new();
+        7u64.encode(&mut bytes).unwrap();
+
+        assert!(u64::decode(&mut &bytes[..4]).is_err());
+        assert!(bool::decode(&mut &[2u8][..]).is_err());
+    }
+}
@@ -1,9 +1,30 @@
 use crate::cfr::game_model::{Probability, VisibleInfo};
+use fastrand::Rng;
 
 pub trait GamestateSampler: Clone + Send {
     type Info: VisibleInfo;
 
-    fn sample(&mut self) -> (<Self::Info as VisibleInfo>::Gamestate, Probability);
+    /// Draws a world, using only `rng` for randomness so runs can be reproduced
+    fn sample(&mut self, rng: &mut Rng) -> (<Self::Info as VisibleInfo>::Gamestate, Probability);
+
+    /// How likely [`Self::sample`] is to draw `gamestate`, which it said has `probability`
+    ///
+    /// Training weighs each world drawn by `probability` over this, so worlds drawn more often
+    /// than they come up don't count for more. Samplers that draw worlds as often as they come
+    /// up don't need to override this.
+    fn sampling_probability(
+        &self,
+        _gamestate: &<Self::Info as VisibleInfo>::Gamestate,
+        probability: Probability,
+    ) -> Probability {
+        probability
+    }
+}
+
+/// A sampler with few enough worlds that they can all be listed out
+pub trait EnumerableGamestateSampler: GamestateSampler {
+    /// Every world the sampler could produce, along with its probability
+    fn enumerate(&self) -> Vec<(<Self::Info as VisibleInfo>::Gamestate, Probability)>;
 }
 
 #[derive(Debug)]
@@ -15,16 +36,23 @@ pub struct RandomGamestateIterator<SAMPLER: GamestateSampler> {
     stopping_n: u32,
 
     sampler: SAMPLER,
+    rng: Rng,
 }
 
 impl<SAMPLER: GamestateSampler> RandomGamestateIterator<SAMPLER> {
-    pub fn new(sampler: SAMPLER, stopping_probability: Probability, stopping_n: u32) -> Self {
+    pub fn new(
+        sampler: SAMPLER,
+        rng: Rng,
+        stopping_probability: Probability,
+        stopping_n: u32,
+    ) -> Self {
         Self {
             cumulative_probability: 0.0,
             stopping_probability,
             cumulative_n: 0,
             stopping_n,
             sampler,
+            rng,
         }
     }
 }
@@ -42,7 +70,7 @@ impl<SAMPLER: GamestateSampler> Iterator for RandomGamestateIterator<SAMPLER> {
             return None;
         }
 
-        let (g, p) = self.sampler.sample();
+        let (g, p) = self.sampler.sample(&mut self.rng);
         self.cumulative_probability += p;
         self.cumulative_n += 1;
 
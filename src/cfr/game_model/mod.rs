@@ -1,8 +1,10 @@
+mod encoding;
 mod gamestate_sampler;
 mod oracle_gamestate;
 mod utility;
 mod visible_info;
 
+pub use encoding::*;
 pub use gamestate_sampler::*;
 pub use oracle_gamestate::*;
 pub use utility::*;
@@ -1,13 +1,26 @@
-use crate::cfr::game_model::{PlayerNumber, VisibleInfo};
+use crate::cfr::game_model::{PlayerNumber, Probability, VisibleInfo};
 use std::fmt::Debug;
 use std::hash::Hash;
 
-pub trait OracleGamestate<INFO: VisibleInfo>: Hash + Clone + Debug + Sized + Send + Sync {
+pub trait OracleGamestate<INFO: VisibleInfo>:
+    Hash + Eq + Clone + Debug + Sized + Send + Sync
+{
     fn info_for_turn_player(&self) -> INFO;
 
+    /// What `player` can see of this gamestate, whether or not it's their turn
+    fn info_for_player(&self, player: PlayerNumber) -> INFO;
+
     fn players_playing(&self) -> PlayerNumber;
 
     fn turn(&self) -> PlayerNumber;
 
     fn advance(&self, m: &INFO::Move) -> Self;
+
+    /// If chance acts here rather than a player, every outcome along with its probability
+    ///
+    /// `info_for_turn_player`, `turn` and `advance` are never called on a gamestate where chance
+    /// acts.
+    fn chance_outcomes(&self) -> Option<Vec<(Self, Probability)>> {
+        None
+    }
 }
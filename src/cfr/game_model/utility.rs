@@ -7,9 +7,9 @@ pub type AtomicUtility = AtomicF64;
 
 // FIXME: Should be a trait implemented for arrays or Vec
 
-const MAX_PLAYERS: usize = 4;
+pub(crate) const MAX_PLAYERS: usize = 4;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct UtilityForAllPlayers {
     pub(crate) util: [Utility; MAX_PLAYERS],
 }
@@ -41,11 +41,3 @@ impl UtilityForAllPlayers {
         }
     }
 }
-
-impl Default for UtilityForAllPlayers {
-    fn default() -> Self {
-        Self {
-            util: Default::default(),
-        }
-    }
-}
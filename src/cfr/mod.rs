@@ -1,2 +1,4 @@
+pub mod gadget;
 pub mod game_model;
+pub mod strategy_evaluation;
 pub mod strategy_generation;
@@ -0,0 +1,410 @@
+use crate::cfr::game_model::{
+    EnumerableGamestateSampler, OracleGamestate, PlayerNumber, Probability, Utility,
+    UtilityForAllPlayers, VisibleInfo,
+};
+use rustc_hash::FxHashMap;
+use std::rc::Rc;
+
+/// How far a strategy is from an equilibrium
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exploitability {
+    pub players_playing: PlayerNumber,
+    /// What each player gets when everyone plays the strategy
+    pub strategy_value: UtilityForAllPlayers,
+    /// What each player gets by playing a best response, while everyone else keeps to the strategy
+    pub best_response_value: UtilityForAllPlayers,
+}
+
+impl Exploitability {
+    /// How much `player` could gain by deviating from the strategy
+    pub fn gain(&self, player: PlayerNumber) -> Utility {
+        self.best_response_value.get(player) - self.strategy_value.get(player)
+    }
+
+    /// How much every player together could gain by deviating. Zero exactly at a Nash equilibrium
+    pub fn nash_conv(&self) -> Utility {
+        (0..self.players_playing).map(|p| self.gain(p)).sum()
+    }
+
+    /// [`Self::nash_conv`] averaged over the players
+    pub fn exploitability(&self) -> Utility {
+        self.nash_conv() / self.players_playing as Utility
+    }
+}
+
+/// Exactly evaluates a strategy against a best response from each player
+///
+/// `strategy` gives the probability of each move in an infoset. This walks the entire game tree
+/// for every world of the sampler, so only use it on games small enough to enumerate.
+pub fn evaluate_strategy<SAMPLER, STRATEGY>(sampler: &SAMPLER, strategy: STRATEGY) -> Exploitability
+where
+    SAMPLER: EnumerableGamestateSampler,
+    STRATEGY: FnMut(SAMPLER::Info) -> Vec<(<SAMPLER::Info as VisibleInfo>::Move, Probability)>,
+{
+    let worlds = sampler.enumerate();
+    let total_probability: Probability = worlds.iter().map(|(_, p)| p).sum();
+    let worlds: Vec<_> = worlds
+        .into_iter()
+        .map(|(world, p)| (world, p / total_probability))
+        .collect();
+
+    let mut evaluator = StrategyEvaluator {
+        strategy,
+        nodes: FxHashMap::default(),
+    };
+
+    let players_playing = worlds
+        .first()
+        .map(|(world, _)| world.players_playing())
+        .unwrap_or(0);
+
+    let mut strategy_value = UtilityForAllPlayers::default();
+    let mut strategy_values = FxHashMap::default();
+    for (world, p) in &worlds {
+        strategy_value.accumulate(&evaluator.strategy_value(world, &mut strategy_values), *p);
+    }
+
+    let mut best_response_value = UtilityForAllPlayers::default();
+    for player in 0..players_playing {
+        best_response_value.util[player] = BestResponse::new(player).value(&mut evaluator, &worlds);
+    }
+
+    Exploitability {
+        players_playing,
+        strategy_value,
+        best_response_value,
+    }
+}
+
+/// What each of `worlds` is worth to every player, when `player` best responds to everyone else
+/// playing `strategy`
+///
+/// The probability of each world is how likely chance and everyone but `player` are to reach it,
+/// which decides what the best response does where `player` can't tell worlds apart.
+pub fn best_response_values<INFO, STRATEGY>(
+    worlds: &[(INFO::Gamestate, Probability)],
+    player: PlayerNumber,
+    strategy: STRATEGY,
+) -> Vec<UtilityForAllPlayers>
+where
+    INFO: VisibleInfo,
+    STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+{
+    let mut evaluator = StrategyEvaluator {
+        strategy,
+        nodes: FxHashMap::default(),
+    };
+
+    let mut best_response = BestResponse::new(player);
+    for (world, p) in worlds {
+        best_response.collect_reach(&mut evaluator, world, *p);
+    }
+
+    let mut memo = FxHashMap::default();
+    worlds
+        .iter()
+        .map(|(world, _)| best_response.values_for_all_players(&mut evaluator, world, &mut memo))
+        .collect()
+}
+
+#[derive(Debug)]
+enum Node<INFO: VisibleInfo> {
+    Terminal(UtilityForAllPlayers),
+    Turn(Rc<[(INFO::Move, Probability)]>),
+}
+
+impl<INFO: VisibleInfo> Clone for Node<INFO> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Terminal(utility) => Node::Terminal(*utility),
+            Node::Turn(moves) => Node::Turn(moves.clone()),
+        }
+    }
+}
+
+struct StrategyEvaluator<INFO: VisibleInfo, STRATEGY> {
+    strategy: STRATEGY,
+    nodes: FxHashMap<INFO, Node<INFO>>,
+}
+
+impl<INFO, STRATEGY> StrategyEvaluator<INFO, STRATEGY>
+where
+    INFO: VisibleInfo,
+    STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+{
+    fn node(&mut self, gamestate: &INFO::Gamestate) -> Node<INFO> {
+        let info = gamestate.info_for_turn_player();
+        if let Some(node) = self.nodes.get(&info) {
+            return node.clone();
+        }
+
+        let node = match info.run_for_moves(|_| {}) {
+            Some(utility) => Node::Terminal(utility),
+            None => Node::Turn((self.strategy)(gamestate.info_for_turn_player()).into()),
+        };
+        self.nodes.insert(info, node.clone());
+
+        node
+    }
+
+    fn strategy_value(
+        &mut self,
+        gamestate: &INFO::Gamestate,
+        memo: &mut FxHashMap<INFO::Gamestate, UtilityForAllPlayers>,
+    ) -> UtilityForAllPlayers {
+        if let Some(value) = memo.get(gamestate) {
+            return *value;
+        }
+
+        if let Some(outcomes) = gamestate.chance_outcomes() {
+            let mut value = UtilityForAllPlayers::default();
+            for (outcome, p) in &outcomes {
+                value.accumulate(&self.strategy_value(outcome, memo), *p);
+            }
+            memo.insert(gamestate.clone(), value);
+
+            return value;
+        }
+
+        let value = match self.node(gamestate) {
+            Node::Terminal(utility) => utility,
+            Node::Turn(moves) => {
+                let mut value = UtilityForAllPlayers::default();
+                for (m, p) in moves.iter() {
+                    value.accumulate(&self.strategy_value(&gamestate.advance(m), memo), *p);
+                }
+                value
+            }
+        };
+        memo.insert(gamestate.clone(), value);
+
+        value
+    }
+}
+
+/// A best response for a single player, against everyone else playing the strategy
+///
+/// Works in two passes. The first goes forward through the tree to find how likely chance and
+/// everyone else are to reach each of the player's gamestates. The second picks, for each of the player's
+/// infosets, the move that does best across all of the gamestates in it weighted by that reach.
+struct BestResponse<INFO: VisibleInfo> {
+    player: PlayerNumber,
+    reach_of_others: FxHashMap<INFO, FxHashMap<INFO::Gamestate, Probability>>,
+    best_moves: FxHashMap<INFO, INFO::Move>,
+    values: FxHashMap<INFO::Gamestate, Utility>,
+}
+
+impl<INFO: VisibleInfo> BestResponse<INFO> {
+    fn new(player: PlayerNumber) -> Self {
+        Self {
+            player,
+            reach_of_others: FxHashMap::default(),
+            best_moves: FxHashMap::default(),
+            values: FxHashMap::default(),
+        }
+    }
+
+    fn value<STRATEGY>(
+        mut self,
+        evaluator: &mut StrategyEvaluator<INFO, STRATEGY>,
+        worlds: &[(INFO::Gamestate, Probability)],
+    ) -> Utility
+    where
+        STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+    {
+        for (world, p) in worlds {
+            self.collect_reach(evaluator, world, *p);
+        }
+
+        worlds
+            .iter()
+            .map(|(world, p)| p * self.gamestate_value(evaluator, world))
+            .sum()
+    }
+
+    fn collect_reach<STRATEGY>(
+        &mut self,
+        evaluator: &mut StrategyEvaluator<INFO, STRATEGY>,
+        gamestate: &INFO::Gamestate,
+        reach: Probability,
+    ) where
+        STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+    {
+        // Nothing reached with zero probability can change what the best response is worth
+        if reach == 0.0 {
+            return;
+        }
+
+        if let Some(outcomes) = gamestate.chance_outcomes() {
+            for (outcome, p) in &outcomes {
+                self.collect_reach(evaluator, outcome, reach * p);
+            }
+            return;
+        }
+
+        let Node::Turn(moves) = evaluator.node(gamestate) else {
+            return;
+        };
+
+        if gamestate.turn() == self.player {
+            *self
+                .reach_of_others
+                .entry(gamestate.info_for_turn_player())
+                .or_default()
+                .entry(gamestate.clone())
+                .or_default() += reach;
+
+            for (m, _) in moves.iter() {
+                self.collect_reach(evaluator, &gamestate.advance(m), reach);
+            }
+        } else {
+            for (m, p) in moves.iter() {
+                self.collect_reach(evaluator, &gamestate.advance(m), reach * p);
+            }
+        }
+    }
+
+    fn gamestate_value<STRATEGY>(
+        &mut self,
+        evaluator: &mut StrategyEvaluator<INFO, STRATEGY>,
+        gamestate: &INFO::Gamestate,
+    ) -> Utility
+    where
+        STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+    {
+        if let Some(value) = self.values.get(gamestate) {
+            return *value;
+        }
+
+        if let Some(outcomes) = gamestate.chance_outcomes() {
+            let value = outcomes
+                .iter()
+                .map(|(outcome, p)| p * self.gamestate_value(evaluator, outcome))
+                .sum();
+            self.values.insert(gamestate.clone(), value);
+
+            return value;
+        }
+
+        let value = match evaluator.node(gamestate) {
+            Node::Terminal(utility) => utility.get(self.player),
+            Node::Turn(moves) if gamestate.turn() == self.player => {
+                let best_move = self.best_move(evaluator, gamestate, &moves);
+                self.gamestate_value(evaluator, &gamestate.advance(&best_move))
+            }
+            Node::Turn(moves) => moves
+                .iter()
+                .map(|(m, p)| p * self.gamestate_value(evaluator, &gamestate.advance(m)))
+                .sum(),
+        };
+        self.values.insert(gamestate.clone(), value);
+
+        value
+    }
+
+    /// Like [`Self::gamestate_value`], but for every player. Only valid after collecting reach
+    fn values_for_all_players<STRATEGY>(
+        &mut self,
+        evaluator: &mut StrategyEvaluator<INFO, STRATEGY>,
+        gamestate: &INFO::Gamestate,
+        memo: &mut FxHashMap<INFO::Gamestate, UtilityForAllPlayers>,
+    ) -> UtilityForAllPlayers
+    where
+        STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+    {
+        if let Some(values) = memo.get(gamestate) {
+            return *values;
+        }
+
+        let mut values = UtilityForAllPlayers::default();
+        if let Some(outcomes) = gamestate.chance_outcomes() {
+            for (outcome, p) in &outcomes {
+                values.accumulate(&self.values_for_all_players(evaluator, outcome, memo), *p);
+            }
+        } else {
+            match evaluator.node(gamestate) {
+                Node::Terminal(utility) => values = utility,
+                Node::Turn(moves) if gamestate.turn() == self.player => {
+                    let best_move = self.best_move(evaluator, gamestate, &moves);
+                    values = self.values_for_all_players(
+                        evaluator,
+                        &gamestate.advance(&best_move),
+                        memo,
+                    );
+                }
+                Node::Turn(moves) => {
+                    for (m, p) in moves.iter() {
+                        let after_move = gamestate.advance(m);
+                        values.accumulate(
+                            &self.values_for_all_players(evaluator, &after_move, memo),
+                            *p,
+                        );
+                    }
+                }
+            }
+        }
+        memo.insert(gamestate.clone(), values);
+
+        values
+    }
+
+    fn best_move<STRATEGY>(
+        &mut self,
+        evaluator: &mut StrategyEvaluator<INFO, STRATEGY>,
+        gamestate: &INFO::Gamestate,
+        moves: &[(INFO::Move, Probability)],
+    ) -> INFO::Move
+    where
+        STRATEGY: FnMut(INFO) -> Vec<(INFO::Move, Probability)>,
+    {
+        let info = gamestate.info_for_turn_player();
+        if let Some(best_move) = self.best_moves.get(&info) {
+            return *best_move;
+        }
+
+        // Only ever needed once per infoset, so it can be taken out
+        let gamestates_in_info = self.reach_of_others.remove(&info).unwrap_or_default();
+
+        let mut best_move = moves[0].0;
+        let mut best_value = Utility::NEG_INFINITY;
+        for (m, _) in moves {
+            let value: Utility = gamestates_in_info
+                .iter()
+                .map(|(g, reach)| reach * self.gamestate_value(evaluator, &g.advance(m)))
+                .sum();
+
+            if value > best_value {
+                best_move = *m;
+                best_value = value;
+            }
+        }
+        self.best_moves.insert(info, best_move);
+
+        best_move
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn training_reduces_exploitability() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+
+        let untrained = strategy_generator.exploitability(&sampler, Policy::Average);
+        strategy_generator.refine_strategy(sampler.clone(), 200);
+        let trained = strategy_generator.exploitability(&sampler, Policy::Average);
+
+        assert!(trained.nash_conv() > -1e-9);
+        assert!(trained.nash_conv() < untrained.nash_conv() / 2.0);
+    }
+}
@@ -0,0 +1,3 @@
+mod best_response;
+
+pub use best_response::*;
@@ -1,281 +1,569 @@
 use crate::cfr::game_model::{
-    GamestateSampler, OracleGamestate, PlayerNumber, Probability, RandomGamestateIterator,
-    UtilityForAllPlayers, VisibleInfo,
+    GamestateSampler, OracleGamestate, PlayerNumber, Probability, UtilityForAllPlayers, VisibleInfo,
 };
+use crate::cfr::strategy_generation::cfr_config::CfrConfig;
+use crate::cfr::strategy_generation::depth_limit::DepthLimit;
+use crate::cfr::strategy_generation::traversal::{ChanceSampling, MoveSelection, TraversalOptions};
+use crate::cfr::strategy_generation::traverser_schedule::Traversers;
 use crate::cfr::strategy_generation::workspace_data::data_for_infoset::DataForInfoSet;
-use crate::cfr::strategy_generation::workspace_data::data_for_move::DataForMove;
-use crate::cfr::strategy_generation::workspace_data::timestamp::{Timestamp, MAX_BATCH_SIZE};
+use crate::cfr::strategy_generation::workspace_data::move_data::MoveWithData;
+use crate::cfr::strategy_generation::workspace_data::reach_probabilities::ReachProbabilities;
+use crate::cfr::strategy_generation::workspace_data::timestamp::Timestamp;
 use crate::cfr::strategy_generation::workspace_data::{
-    StrategyGenerationProgress, ThreadLocalWorkStack,
+    PickedMoves, StrategyGenerationProgress, ThreadLocalWorkStack, UtilityDestination, WorkItem,
 };
 use bumpalo_herd::{Herd, Member};
+use fastrand::Rng;
 use rayon::iter::IndexedParallelIterator;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-
-pub(crate) fn add_to_regret<'h, INFO: VisibleInfo, SAMPLER: GamestateSampler<Info = INFO>>(
+use std::cell::Cell;
+use tinyvec::TinyVec;
+
+pub(crate) fn add_to_regret<
+    'h,
+    INFO: VisibleInfo,
+    SAMPLER: GamestateSampler<Info = INFO>,
+    CONFIG: CfrConfig,
+>(
     starting_gamestate_sampler: SAMPLER,
     strategy_generation_progress: &StrategyGenerationProgress<'h, INFO>,
     herd: &'h Herd,
     iteration: u32,
+    config: &CONFIG,
+    options: &TraversalOptions,
+    depth_limit: Option<&DepthLimit<INFO>>,
 ) {
-    let mut gamestates = RandomGamestateIterator::new(starting_gamestate_sampler, 1000.0, 10);
-    // let par_gamestates = ParallelBridge::par_bridge(gamestates.enumerate());
+    let batch = options.world_sampling.draw(
+        starting_gamestate_sampler,
+        strategy_generation_progress.batch_size(),
+        &mut Timestamp::new(iteration, 0).rng(options.seed, &"worlds"),
+    );
+
+    batch
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(i, (starting_gamestate, world_weight))| {
+            let member = herd.get();
+
+            let batch_item = BatchItemTraversal {
+                strategy_generation_progress,
+                member: &member,
+                config,
+                options,
+                depth_limit,
+                timestamp: Timestamp::new(iteration, i),
+                seed: options.seed,
+                players_playing: starting_gamestate.players_playing(),
+                traversers: options.schedule.traversers(
+                    iteration,
+                    i,
+                    starting_gamestate.players_playing(),
+                ),
+                moves_pruned: Cell::new(0),
+            };
+
+            batch_item.run(starting_gamestate, world_weight);
+        });
+}
 
-    let mut batch = Vec::new();
-    while batch.len() < MAX_BATCH_SIZE {
-        batch.push(gamestates.next().unwrap())
+/// Everything needed to traverse the game tree for a single item in a batch
+struct BatchItemTraversal<'a, 'h, INFO: VisibleInfo, CONFIG: CfrConfig> {
+    strategy_generation_progress: &'a StrategyGenerationProgress<'h, INFO>,
+    member: &'a Member<'h>,
+    config: &'a CONFIG,
+    options: &'a TraversalOptions,
+    depth_limit: Option<&'a DepthLimit<INFO>>,
+
+    timestamp: Timestamp,
+    seed: u64,
+    players_playing: PlayerNumber,
+    traversers: Traversers,
+    moves_pruned: Cell<usize>,
+}
+
+impl<'h, INFO: VisibleInfo, CONFIG: CfrConfig> BatchItemTraversal<'_, 'h, INFO, CONFIG> {
+    fn run(&self, starting_gamestate: INFO::Gamestate, world_weight: Probability) {
+        let mut workstack = self.strategy_generation_progress.thread_local_workstack();
+
+        self.resolve_chance(
+            starting_gamestate,
+            1.0,
+            self.options.chance_sampling,
+            &mut |starting_gamestate, chance_probability| {
+                self.push_visit(
+                    &mut workstack,
+                    starting_gamestate,
+                    ReachProbabilities::new(world_weight * chance_probability),
+                    UtilityDestination {
+                        move_index: 0,
+                        weight: chance_probability,
+                    },
+                );
+            },
+        );
+
+        while let Some(item) = workstack.pop() {
+            match item {
+                WorkItem::Visit {
+                    data_for_info_set,
+                    gamestate,
+                    reach,
+                    destination,
+                } => self.visit(
+                    &mut workstack,
+                    data_for_info_set,
+                    gamestate,
+                    reach,
+                    destination,
+                ),
+                WorkItem::Complete {
+                    data_for_info_set,
+                    reach,
+                    destination,
+                    picked,
+                    utilities_from,
+                } => self.complete(
+                    &mut workstack,
+                    data_for_info_set,
+                    reach,
+                    destination,
+                    picked,
+                    utilities_from,
+                ),
+            }
+        }
+
+        // Nothing is waiting on the gamestates the traversal started from
+        workstack.clear_utilities();
+        self.strategy_generation_progress
+            .record_workstack_depth(workstack.take_peak_len());
+        self.strategy_generation_progress
+            .record_moves_pruned(self.moves_pruned.get());
     }
 
-    batch.into_par_iter().enumerate().for_each(
-        |(i, (starting_gamestate, gamestate_probability))| {
-            let timestamp = Timestamp::new(iteration, i);
+    /// Pushes the moves to take from `gamestate`, with the item that completes it once they're
+    /// done underneath them. Terminal gamestates have their utility right away
+    fn visit(
+        &self,
+        workstack: &mut ThreadLocalWorkStack<'h, INFO>,
+        data_for_info: &'h DataForInfoSet<INFO>,
+        gamestate: INFO::Gamestate,
+        reach: ReachProbabilities,
+        destination: UtilityDestination,
+    ) {
+        data_for_info.mark_as_visited(self.timestamp);
+
+        if let Some(utility) = data_for_info.terminal_utility() {
+            data_for_info.record_visit();
+            workstack.push_utility(destination, utility);
+            return;
+        }
 
-            let mut workstack = strategy_generation_progress.thread_local_workstack();
+        let turn = data_for_info.turn();
+        let utilities_from = workstack.utilities_len();
+        let exploration = match self.options.traversal.move_selection(turn, self.traversers) {
+            MoveSelection::Expand => {
+                workstack.push(WorkItem::Complete {
+                    data_for_info_set: data_for_info,
+                    reach,
+                    destination,
+                    picked: PickedMoves::Expanded,
+                    utilities_from,
+                });
+                self.expand_moves(workstack, data_for_info, &gamestate, reach);
+                return;
+            }
+            MoveSelection::Sample => None,
+            MoveSelection::Explore(exploration) => Some(exploration),
+        };
+
+        let (next_move, move_probability, sampling_probability) = data_for_info
+            .sample_move_deterministic(self.rng(&gamestate), exploration.unwrap_or(0.0));
+        let move_index = data_for_info
+            .moves()
+            .iter()
+            .position(|move_with_data| move_with_data.m == next_move)
+            .expect("The sampled move is one of the infoset's moves");
+
+        workstack.push(WorkItem::Complete {
+            data_for_info_set: data_for_info,
+            reach,
+            destination,
+            picked: match exploration {
+                Some(_) => PickedMoves::Explored {
+                    move_index,
+                    move_probability,
+                    sampling_probability,
+                },
+                None => PickedMoves::Sampled,
+            },
+            utilities_from,
+        });
+
+        let next_gamestate = self.sample_chance(gamestate.advance(&next_move));
+        self.push_visit(
+            workstack,
+            next_gamestate,
+            reach.after_sampled_move(turn, move_probability, sampling_probability),
+            UtilityDestination {
+                move_index,
+                weight: 1.0,
+            },
+        );
+    }
 
-            let member = herd.get();
+    /// Pushes a visit to `gamestate`, unless it's past the depth limit. The utility there is only
+    /// estimated and there's no regret to update, so it doesn't get an infoset at all
+    fn push_visit(
+        &self,
+        workstack: &mut ThreadLocalWorkStack<'h, INFO>,
+        gamestate: INFO::Gamestate,
+        reach: ReachProbabilities,
+        destination: UtilityDestination,
+    ) {
+        if let Some(utility) = self.depth_limit.and_then(|depth_limit| {
+            depth_limit.leaf_utility(&gamestate, reach.depth(), &mut self.rng(&gamestate))
+        }) {
+            workstack.push_utility(destination, utility);
+            return;
+        }
 
-            let info_set = strategy_generation_progress
-                .get_data_for_infoset(starting_gamestate.info_for_turn_player(), &member);
-            workstack.push(info_set, starting_gamestate);
-
-            let mut n: u32 = 0;
-            let mut already_ready: u32 = 1;
-            let mut terminals: u32 = 1;
-            while let Some((data_for_info, gamestate)) = workstack.pop() {
-                // println!("Gamestate: {:?}", gamestate);
-
-                n += 1;
-                if n.count_ones() == 1 {
-                    // println!(
-                    //     "Timestamp {:?} Iteration 2^{} (already ready 2^{}, terminal 2^{})",
-                    //     timestamp,
-                    //     n.ilog2(),
-                    //     already_ready.ilog2(),
-                    //     terminals.ilog2()
-                    // );
-                    // // println!("Gamestate: {:?}", gamestate);
-                    // println!("Stack size: {:?}", workstack.len());
-                    //
-                    // if n == 1 {
-                    //     println!(
-                    //         "Size DataForInfoset {:?}",
-                    //         size_of::<DataForInfoSet<INFO>>()
-                    //     );
-                    //     println!("Size DataForMove {:?}", size_of::<DataForMove>());
-                    //     println!("Size Info {:?}", size_of::<INFO>());
-                    //     println!("Size Gamestate {:?}", size_of::<INFO::Gamestate>());
-                    // }
-                    // // workstack.print_debug();
-                    //
-                    // if n.ilog2() == 25 {
-                    //     panic!("CAPPED!")
-                    // }
-                }
+        workstack.push(WorkItem::Visit {
+            data_for_info_set: self
+                .strategy_generation_progress
+                .get_data_for_infoset(gamestate.info_for_turn_player(), self.member),
+            gamestate,
+            reach,
+            destination,
+        });
+    }
 
-                // If we already got this value ready, we can skip it
-                // FIXME: Is this pointless? Won't we only push if it's not ready?
-                if data_for_info.is_ready(timestamp) {
-                    already_ready += 1;
-                    continue;
-                }
+    fn expand_moves(
+        &self,
+        workstack: &mut ThreadLocalWorkStack<'h, INFO>,
+        data_for_info: &'h DataForInfoSet<INFO>,
+        gamestate: &INFO::Gamestate,
+        reach: ReachProbabilities,
+    ) {
+        debug_assert!(data_for_info.move_count() > 0);
+        let turn = data_for_info.turn();
+        for (move_index, move_with_data) in data_for_info.moves().iter().enumerate() {
+            if self.is_pruned(data_for_info, move_with_data) {
+                self.moves_pruned.set(self.moves_pruned.get() + 1);
+                continue;
+            }
 
-                // If the gamestate is terminal, we need to mark it as terminal
-                if data_for_info.increment_terminal_counterfactual(gamestate_probability, timestamp)
-                {
-                    terminals += 1;
-                    strategy_generation_progress.mark_as_completed(data_for_info, timestamp);
-                    continue;
-                }
+            let move_probability = move_with_data
+                .d
+                .load_move_probability(data_for_info.move_count());
+            let reach_after_move = reach.after_move(turn, move_probability);
+            self.resolve_chance(
+                gamestate.advance(&move_with_data.m),
+                1.0,
+                self.options.chance_sampling,
+                &mut |state_after_move, chance_probability| {
+                    self.push_visit(
+                        workstack,
+                        state_after_move,
+                        reach_after_move.after_chance(chance_probability),
+                        UtilityDestination {
+                            move_index,
+                            weight: chance_probability,
+                        },
+                    );
+                },
+            );
+        }
+    }
+
+    /// Once every move picked at a gamestate is done, works out its utility and the regret of
+    /// each move, and passes the utility on to the gamestate before it
+    fn complete(
+        &self,
+        workstack: &mut ThreadLocalWorkStack<'h, INFO>,
+        info_before_move: &'h DataForInfoSet<INFO>,
+        reach: ReachProbabilities,
+        destination: UtilityDestination,
+        picked: PickedMoves,
+        utilities_from: usize,
+    ) {
+        let mut utility_after_move: TinyVec<[UtilityForAllPlayers; 16]> = info_before_move
+            .moves()
+            .iter()
+            .map(|_| UtilityForAllPlayers::default())
+            .collect();
+        for (from, utility) in workstack.take_utilities_from(utilities_from) {
+            utility_after_move[from.move_index].accumulate(&utility, from.weight);
+        }
 
-                // // FIXME: Make this configurable
-                if (iteration as PlayerNumber + i) % gamestate.players_playing()
-                    != data_for_info.turn()
+        let n_moves = info_before_move.move_count();
+        let mut strategy_util = UtilityForAllPlayers::default();
+        match picked {
+            PickedMoves::Expanded => {
+                for (move_with_data, utility) in
+                    info_before_move.moves().iter().zip(&utility_after_move)
                 {
-                    let next_move = data_for_info.sample_move_deterministic(&gamestate, timestamp);
-                    let next_gamestate = gamestate.advance(&next_move);
-                    let next_info = strategy_generation_progress
-                        .get_data_for_infoset(next_gamestate.info_for_turn_player(), &member);
-
-                    let forwardable_iteration_util =
-                        next_info.get_iteration_utility_if_ready(timestamp);
-
-                    match forwardable_iteration_util {
-                        Some(x) => data_for_info.ready_with_counterfactual(
-                            x,
-                            gamestate_probability,
-                            timestamp,
-                        ),
-                        None => workstack.push(data_for_info, gamestate),
+                    if !self.is_pruned(info_before_move, move_with_data) {
+                        strategy_util
+                            .accumulate(utility, move_with_data.d.load_move_probability(n_moves));
                     }
-
-                    workstack.push(next_info, next_gamestate);
-                    continue;
                 }
+            }
+            // The sampled move stands in for the strategy, so its utility passes straight through
+            PickedMoves::Sampled => {
+                for utility in &utility_after_move {
+                    strategy_util.accumulate(utility, 1.0);
+                }
+            }
+            PickedMoves::Explored {
+                move_index,
+                move_probability,
+                sampling_probability,
+            } => {
+                // Importance sampling: the sampled move stands in for every move, so scale it up
+                // by how unlikely it was to be sampled, and treat the moves that weren't as worth
+                // nothing
+                utility_after_move[move_index].reduce(1.0 / sampling_probability);
+                strategy_util.accumulate(&utility_after_move[move_index], move_probability);
+            }
+        }
+
+        info_before_move.record_visit();
+        workstack.push_utility(destination, strategy_util);
+
+        let turn = info_before_move.turn();
+        if self.averages_at(turn) {
+            self.accumulate_average_strategy(info_before_move, reach);
+        }
+
+        // Moves get expanded for other players too when traversing the full tree, but only the
+        // traversers' regret is updated
+        if !self.traversers.contains(turn) {
+            return;
+        }
+
+        let counterfactual_weight = reach.counterfactual_weight(turn);
+        for (move_index, (move_with_data, utility)) in info_before_move
+            .moves()
+            .iter()
+            .zip(&utility_after_move)
+            .enumerate()
+        {
+            if self.is_pruned(info_before_move, move_with_data) {
+                continue;
+            }
+
+            let instant_regret =
+                counterfactual_weight * (utility.get(turn) - strategy_util.get(turn));
+            info_before_move.accumulate_regret(move_index, instant_regret, self.timestamp);
+        }
+
+        self.strategy_generation_progress
+            .mark_as_completed(info_before_move, self.timestamp);
+    }
 
-                // Save a spot for this item, in case the moves bellow need done first
-                workstack.push(data_for_info, gamestate.clone());
-
-                let moves = data_for_info.moves();
-                let n_moves = moves.len();
-                let mut strategy_util = UtilityForAllPlayers::default();
-                let mut complete = true;
-                debug_assert!(data_for_info.move_count() > 0);
-                for move_with_data in moves {
-                    update_strategy_utility_for_move(
-                        strategy_generation_progress,
-                        &member,
-                        &mut *workstack,
-                        timestamp,
-                        &gamestate,
-                        n_moves,
-                        &move_with_data.m,
-                        &move_with_data.d,
-                        &mut strategy_util,
-                        &mut complete,
+    /// Calls `f` with every gamestate where a player acts that `gamestate` could lead to through
+    /// chance, along with how much it counts towards the utility of `gamestate`
+    ///
+    /// Enumerated outcomes count by their probability. A sampled outcome stands in for all of
+    /// them, so it counts fully.
+    fn resolve_chance(
+        &self,
+        gamestate: INFO::Gamestate,
+        probability: Probability,
+        chance_sampling: ChanceSampling,
+        f: &mut impl FnMut(INFO::Gamestate, Probability),
+    ) {
+        match (gamestate.chance_outcomes(), chance_sampling) {
+            (None, _) => f(gamestate, probability),
+            (Some(outcomes), ChanceSampling::Enumerate) => {
+                for (outcome, outcome_probability) in outcomes {
+                    self.resolve_chance(
+                        outcome,
+                        probability * outcome_probability,
+                        chance_sampling,
+                        f,
                     );
                 }
+            }
+            (Some(_), ChanceSampling::Sample) => {
+                f(self.sample_chance(gamestate), probability);
+            }
+        }
+    }
 
-                if complete {
-                    // Retrieve from being pushed earlier in the function
-                    workstack.pop();
-                    accumulate_regret_with_complete_children(
-                        strategy_generation_progress,
-                        &member,
-                        timestamp,
-                        gamestate_probability,
-                        data_for_info,
-                        &gamestate,
-                        strategy_util,
-                    );
+    /// Samples chance outcomes until reaching a gamestate where a player acts
+    fn sample_chance(&self, mut gamestate: INFO::Gamestate) -> INFO::Gamestate {
+        while let Some(outcomes) = gamestate.chance_outcomes() {
+            let mark = self.rng(&gamestate).f64();
+
+            let mut cumulative = 0.0;
+            let n_outcomes = outcomes.len();
+            for (i, (outcome, outcome_probability)) in outcomes.into_iter().enumerate() {
+                cumulative += outcome_probability;
+
+                // Rounding can leave the probabilities a hair short of adding up to one
+                if mark < cumulative || i + 1 == n_outcomes {
+                    gamestate = outcome;
+                    break;
                 }
             }
-        },
-    );
-}
+        }
 
-fn update_strategy_utility_for_move<'h, INFO: VisibleInfo>(
-    strategy_generation_progress: &StrategyGenerationProgress<'h, INFO>,
-    member: &Member<'h>,
-    workstack: &mut ThreadLocalWorkStack<'h, INFO>,
+        gamestate
+    }
 
-    timestamp: Timestamp,
+    fn is_pruned(
+        &self,
+        data_for_info: &DataForInfoSet<INFO>,
+        move_with_data: &MoveWithData<INFO>,
+    ) -> bool {
+        self.options.pruning.is_some_and(|pruning| {
+            pruning.prunes(
+                self.timestamp.cfr_iteration,
+                &move_with_data.d,
+                data_for_info.move_count(),
+            )
+        })
+    }
 
-    gamestate_before_move: &INFO::Gamestate,
-    n_moves: usize,
+    fn rng(&self, gamestate: &INFO::Gamestate) -> Rng {
+        self.timestamp.rng(self.seed, gamestate)
+    }
 
-    m: &INFO::Move,
-    data_for_move: &DataForMove,
-    strategy_util: &mut UtilityForAllPlayers,
-    complete: &mut bool,
-) {
-    let move_probability = data_for_move.load_move_probability(n_moves);
+    fn averages_at(&self, turn: PlayerNumber) -> bool {
+        self.options
+            .traversal
+            .averages_at(turn, self.traversers, self.players_playing)
+    }
 
-    let cached_utility_after_move = data_for_move.utility_after_move();
-    if let Some(util) = cached_utility_after_move.get(timestamp) {
-        strategy_util.accumulate(&util, move_probability);
-        return;
+    fn accumulate_average_strategy(
+        &self,
+        data_for_info: &'h DataForInfoSet<INFO>,
+        reach: ReachProbabilities,
+    ) {
+        let turn = data_for_info.turn();
+        data_for_info.accumulate_average_strategy(
+            reach.sampled_player_reach(turn)
+                * self
+                    .config
+                    .average_strategy_weight(self.timestamp.cfr_iteration),
+            self.timestamp,
+        );
+        self.strategy_generation_progress
+            .mark_as_completed(data_for_info, self.timestamp);
     }
+}
 
-    let (data_after_move, state_after_move) = data_for_move.get_post_move_infoset(
-        strategy_generation_progress,
-        member,
-        gamestate_before_move,
-        m,
-    );
+#[cfg(test)]
+mod test {
+    use crate::cfr::game_model::{
+        GamestateSampler, OracleGamestate, PlayerNumber, Probability, UtilityForAllPlayers,
+        VisibleInfo,
+    };
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::Traversal;
+    use crate::cfr::strategy_generation::traverser_schedule::Simultaneous;
+    use bumpalo_herd::Herd;
+
+    /// Player 0 picks either of two moves that both lead to the same spot, where player 1 picks
+    /// left to win or right to draw
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum TwoPaths {
+        Start,
+        Middle,
+        End { left: bool },
+    }
 
-    let utility_after_move = data_after_move.get_iteration_utility_if_ready(timestamp);
-    match utility_after_move {
-        Some(utility_after_move) => {
-            strategy_util.accumulate(&utility_after_move, move_probability);
-            cached_utility_after_move.set(utility_after_move, timestamp);
+    impl OracleGamestate<TwoPaths> for TwoPaths {
+        fn info_for_turn_player(&self) -> TwoPaths {
+            *self
         }
-        None => {
-            // if should_skip_due_to_mccfr(n_moves, data_for_move) {
-            //     // strategy_util.accumulate(&data_after_move.get_cumulative_counterfactual(), move_probability);
-            //     // cached_utility_after_move.set(data_after_move.get_cumulative_counterfactual(), timestamp);
-            //     let default = UtilityForAllPlayers::const_default();
-            //     strategy_util.accumulate(&default, move_probability);
-            //     cached_utility_after_move.set(default, timestamp);
-            //     return;
-            // }
-
-            *complete = false;
-            workstack.push(data_after_move, state_after_move);
+
+        fn info_for_player(&self, _player: PlayerNumber) -> TwoPaths {
+            *self
+        }
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            VisibleInfo::turn(self)
+        }
+
+        fn advance(&self, m: &bool) -> Self {
+            match self {
+                TwoPaths::Start => TwoPaths::Middle,
+                TwoPaths::Middle => TwoPaths::End { left: *m },
+                TwoPaths::End { .. } => unreachable!(),
+            }
         }
     }
-}
 
-fn should_skip_due_to_mccfr(n_moves: usize, move_data: &DataForMove) -> bool {
-    let epsilon = 0.05;
-    let gamma = 2.0;
-    // let gamma = 1.5;
-    let beta = (0.0 as Probability).powi(1);
+    impl VisibleInfo for TwoPaths {
+        type Move = bool;
+        type Gamestate = TwoPaths;
 
-    let prob = move_data.load_move_probability(n_moves);
-    let mccfr_value = ((beta + gamma * prob) / (beta + 1.0)).max(epsilon);
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
 
-    fastrand::f64() > mccfr_value
-}
+        fn turn(&self) -> PlayerNumber {
+            match self {
+                TwoPaths::Middle => 1,
+                _ => 0,
+            }
+        }
 
-// fn should_skip_due_to_mccfr<INFO: VisibleInfo>(
-//     progress_data: &CfrProgressData<INFO>,
-//     info_before_move: &INFO,
-//     m: &INFO::Move,
-// ) -> bool {
-//     let epsilon = 0.05;
-//     let beta = (1.0 as Utility).powi(1);
-//     let gamma = 1000.0;
-//
-//     // Technically in MCCFR you're supposed to use cumulative counterfactual for this
-//     // But since we're in CFR+ we don't really have that and this is approximately the same thing
-//     let move_regret = progress_data
-//         .cumulative_regret
-//         .get_regret_for_all_moves_or_default(info_before_move);
-//     let this_move_regret = move_regret.get_move_regret(m);
-//     let total_regret = move_regret.get_total_regret();
-//
-//     // We want to release this lock ASAP!
-//     drop(move_regret);
-//
-//     let p_numerator = beta + gamma * this_move_regret;
-//     let p_denominator = beta + total_regret;
-//     let p = (p_numerator / p_denominator).max(epsilon);
-//
-//     fastrand::f64() > p
-// }
-
-fn accumulate_regret_with_complete_children<'h, INFO: VisibleInfo>(
-    strategy_generation_progress: &StrategyGenerationProgress<'h, INFO>,
-    member: &Member<'h>,
-    timestamp: Timestamp,
+        fn run_for_moves(&self, mut f: impl FnMut(bool)) -> Option<UtilityForAllPlayers> {
+            match self {
+                TwoPaths::End { left } => {
+                    let win = if *left { 1.0 } else { 0.0 };
+                    Some(UtilityForAllPlayers {
+                        util: [-win, win, 0.0, 0.0],
+                    })
+                }
+                _ => {
+                    f(true);
+                    f(false);
+                    None
+                }
+            }
+        }
+    }
 
-    starting_gamestate_probability: Probability,
+    #[derive(Debug, Clone)]
+    struct TwoPathsSampler;
 
-    info_before_move: &'h DataForInfoSet<INFO>,
-    gamestate_before_move: &INFO::Gamestate,
-    strategy_util: UtilityForAllPlayers,
-) {
-    // We need to do three steps here:
-    // - Update the counterfactual values for this specific item on the item itself
-    // - Update the regret by accumulating the regret on the move using the counterfactual values
-    // - Mark this item as complete
-
-    info_before_move.ready_with_counterfactual(
-        strategy_util,
-        starting_gamestate_probability,
-        timestamp,
-    );
+    impl GamestateSampler for TwoPathsSampler {
+        type Info = TwoPaths;
 
-    for move_with_data in info_before_move.moves() {
-        move_with_data.d.accumulate_regret(
-            strategy_generation_progress,
-            member,
-            timestamp,
-            info_before_move,
-            gamestate_before_move,
-            &move_with_data.m,
-        )
+        fn sample(&mut self, _rng: &mut fastrand::Rng) -> (TwoPaths, Probability) {
+            (TwoPaths::Start, 1.0)
+        }
     }
 
-    strategy_generation_progress.mark_as_completed(info_before_move, timestamp);
+    #[test]
+    fn every_path_into_an_infoset_adds_regret() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_traversal(Traversal::Full)
+            .with_traverser_schedule(Simultaneous)
+            .with_batch_size(1);
+        strategy_generator.refine_strategy(TwoPathsSampler, 1);
+
+        let strategy = strategy_generator.into_strategy();
+        let middle = strategy.infosets.get(&TwoPaths::Middle).unwrap();
+        assert_eq!(middle.visits(), 2);
+
+        // Each path reaches the middle half the time, and left beats the uniform strategy there by
+        // 1/2. Linear CFR weighs the first iteration by 1 and clips the negative regret of right
+        let [left, right] = middle.moves() else {
+            unreachable!()
+        };
+        assert!(left.m);
+        assert!((left.d.regret() - 0.5).abs() < 1e-9);
+        assert_eq!(right.d.regret(), 0.0);
+    }
 }
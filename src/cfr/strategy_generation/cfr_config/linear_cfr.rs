@@ -0,0 +1,15 @@
+use crate::cfr::game_model::Probability;
+use crate::cfr::strategy_generation::averaging::StrategyAveraging;
+use crate::cfr::strategy_generation::cfr_config::CfrConfig;
+
+/// Linear CFR with instantaneous regret clamped to be non-negative
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LinearCfr {
+    pub averaging: StrategyAveraging,
+}
+
+impl CfrConfig for LinearCfr {
+    fn average_strategy_weight(&self, iteration: u32) -> Probability {
+        self.averaging.weight(iteration)
+    }
+}
@@ -0,0 +1,86 @@
+mod discounted_cfr;
+mod linear_cfr;
+mod predictive_cfr;
+
+pub use discounted_cfr::*;
+pub use linear_cfr::*;
+pub use predictive_cfr::*;
+
+use crate::cfr::game_model::{Probability, Utility};
+
+/// The decisions that distinguish one CFR variant from another
+///
+/// Every method has a default matching plain linear CFR, so an implementation only needs to
+/// override the parts it wants to change.
+pub trait CfrConfig: Send + Sync {
+    /// Applied to each instantaneous regret before it is weighted
+    fn clip_instant_regret(&self, instant_regret: Utility) -> Utility {
+        instant_regret.max(0.0)
+    }
+
+    /// The amount to add to a move's cumulative regret for one (clipped) instantaneous regret
+    fn weigh_instant_regret(&self, clipped_regret: Utility, iteration: u32) -> Utility {
+        clipped_regret * iteration as Utility
+    }
+
+    /// The cumulative regret to carry forward once `iteration` is complete
+    fn discount_cumulative_regret(&self, cumulative_regret: Utility, _iteration: u32) -> Utility {
+        cumulative_regret
+    }
+
+    /// How much the strategy from `iteration` counts towards the average strategy
+    fn average_strategy_weight(&self, iteration: u32) -> Probability {
+        iteration as Probability
+    }
+
+    /// The regret the next strategy is computed from, given the cumulative regret and the regret
+    /// added to it over the iteration just finished
+    ///
+    /// The default ignores the latest regret. Optimistic variants count it again, as a prediction
+    /// of the regret the next iteration will add.
+    fn regret_for_strategy(&self, cumulative_regret: Utility, _latest_regret: Utility) -> Utility {
+        cumulative_regret
+    }
+
+    /// Turns the cumulative regret of every move at an infoset into the next strategy
+    ///
+    /// The default is regret matching: play in proportion to positive regret, or uniformly if
+    /// no move has any.
+    fn strategy_from_regret(&self, regrets: &[Utility], strategy: &mut [Probability]) {
+        regret_matching(regrets, strategy)
+    }
+}
+
+/// Plays each move in proportion to its positive regret, or uniformly if no move has any
+pub fn regret_matching(regrets: &[Utility], strategy: &mut [Probability]) {
+    debug_assert_eq!(regrets.len(), strategy.len());
+
+    let total_regret: Utility = regrets.iter().map(|r| r.max(0.0)).sum();
+
+    for (r, p) in regrets.iter().zip(strategy.iter_mut()) {
+        *p = if total_regret > 0.0 {
+            r.max(0.0) / total_regret
+        } else {
+            1.0 / regrets.len() as Probability
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::cfr_config::regret_matching;
+
+    #[test]
+    fn regret_matching_ignores_negative_regret() {
+        let mut strategy = [0.0; 3];
+        regret_matching(&[3.0, -5.0, 1.0], &mut strategy);
+        assert_eq!(strategy, [0.75, 0.0, 0.25]);
+    }
+
+    #[test]
+    fn regret_matching_is_uniform_without_positive_regret() {
+        let mut strategy = [0.0; 4];
+        regret_matching(&[0.0, -1.0, -2.0, 0.0], &mut strategy);
+        assert_eq!(strategy, [0.25; 4]);
+    }
+}
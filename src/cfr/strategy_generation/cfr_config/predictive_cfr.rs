@@ -0,0 +1,108 @@
+use crate::cfr::game_model::{Probability, Utility};
+use crate::cfr::strategy_generation::averaging::StrategyAveraging;
+use crate::cfr::strategy_generation::cfr_config::CfrConfig;
+
+/// Farina, Kroer and Sandholm's Predictive CFR+
+///
+/// Cumulative regret is floored at zero after every iteration, as in CFR+. The next strategy is
+/// computed from the cumulative regret plus the regret from the iteration just finished, used
+/// as a prediction of the regret the next iteration will add.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictiveCfrPlus {
+    pub averaging: StrategyAveraging,
+}
+
+impl Default for PredictiveCfrPlus {
+    /// The paper pairs it with quadratic averaging
+    fn default() -> Self {
+        Self {
+            averaging: StrategyAveraging::Quadratic,
+        }
+    }
+}
+
+impl CfrConfig for PredictiveCfrPlus {
+    fn clip_instant_regret(&self, instant_regret: Utility) -> Utility {
+        instant_regret
+    }
+
+    fn weigh_instant_regret(&self, clipped_regret: Utility, _iteration: u32) -> Utility {
+        clipped_regret
+    }
+
+    fn discount_cumulative_regret(&self, cumulative_regret: Utility, _iteration: u32) -> Utility {
+        cumulative_regret.max(0.0)
+    }
+
+    fn average_strategy_weight(&self, iteration: u32) -> Probability {
+        self.averaging.weight(iteration)
+    }
+
+    fn regret_for_strategy(&self, cumulative_regret: Utility, latest_regret: Utility) -> Utility {
+        cumulative_regret + latest_regret
+    }
+}
+
+/// Optimistic regret matching: like [`PredictiveCfrPlus`], but cumulative regret is left signed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OptimisticCfr {
+    pub averaging: StrategyAveraging,
+}
+
+impl CfrConfig for OptimisticCfr {
+    fn clip_instant_regret(&self, instant_regret: Utility) -> Utility {
+        instant_regret
+    }
+
+    fn weigh_instant_regret(&self, clipped_regret: Utility, _iteration: u32) -> Utility {
+        clipped_regret
+    }
+
+    fn average_strategy_weight(&self, iteration: u32) -> Probability {
+        self.averaging.weight(iteration)
+    }
+
+    fn regret_for_strategy(&self, cumulative_regret: Utility, latest_regret: Utility) -> Utility {
+        cumulative_regret + latest_regret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::cfr_config::{CfrConfig, LinearCfr, PredictiveCfrPlus};
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::Traversal;
+    use crate::tic_tac_toe::test::board_with_x_in_the_middle;
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn the_latest_regret_is_counted_again() {
+        let config = PredictiveCfrPlus::default();
+        assert_eq!(config.regret_for_strategy(2.0, -3.0), -1.0);
+        assert_eq!(config.discount_cumulative_regret(-1.0, 5), 0.0);
+    }
+
+    #[test]
+    fn predictive_cfr_plus_converges_faster_than_linear_cfr() {
+        let herd = Herd::new();
+        let sampler = TicTacToeSampler {
+            board: board_with_x_in_the_middle(),
+        };
+
+        let linear = StrategyGenerator::with_config(&herd, LinearCfr::default())
+            .with_traversal(Traversal::Full);
+        linear.refine_strategy(sampler.clone(), 50);
+        let predictive = StrategyGenerator::with_config(&herd, PredictiveCfrPlus::default())
+            .with_traversal(Traversal::Full);
+        predictive.refine_strategy(sampler.clone(), 50);
+
+        assert!(
+            predictive
+                .exploitability(&sampler, Policy::Average)
+                .nash_conv()
+                < linear.exploitability(&sampler, Policy::Average).nash_conv() / 10.0
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::cfr::game_model::{OracleGamestate, UtilityForAllPlayers, VisibleInfo};
+use dashmap::DashMap;
+use fastrand::Rng;
+
+/// Where a depth-limited traversal stops expanding the game tree
+pub trait LeafCondition<INFO: VisibleInfo>: Send + Sync {
+    /// Whether to stop at `gamestate`, which is `depth` moves past where the traversal started
+    fn is_leaf(&self, gamestate: &INFO::Gamestate, depth: u32) -> bool;
+}
+
+/// Estimates the utility of a gamestate the traversal stopped at
+pub trait LeafEvaluator<INFO: VisibleInfo>: Send + Sync {
+    /// Any randomness should come from `rng`, so runs can be reproduced
+    fn evaluate(&self, gamestate: &INFO::Gamestate, rng: &mut Rng) -> UtilityForAllPlayers;
+}
+
+/// Stops the traversal a fixed number of moves in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxDepth(pub u32);
+
+impl<INFO: VisibleInfo> LeafCondition<INFO> for MaxDepth {
+    fn is_leaf(&self, _gamestate: &INFO::Gamestate, depth: u32) -> bool {
+        depth >= self.0
+    }
+}
+
+/// Cuts off the game tree, using an estimate for the utility past the cutoff
+///
+/// The condition gets asked about each gamestate on its own, so gamestates in the same infoset can
+/// get different answers.
+pub struct DepthLimit<INFO: VisibleInfo> {
+    condition: Box<dyn LeafCondition<INFO>>,
+    evaluator: Box<dyn LeafEvaluator<INFO>>,
+}
+
+impl<INFO: VisibleInfo> DepthLimit<INFO> {
+    pub fn new(
+        condition: impl LeafCondition<INFO> + 'static,
+        evaluator: impl LeafEvaluator<INFO> + 'static,
+    ) -> Self {
+        Self {
+            condition: Box::new(condition),
+            evaluator: Box::new(evaluator),
+        }
+    }
+
+    pub(crate) fn leaf_utility(
+        &self,
+        gamestate: &INFO::Gamestate,
+        depth: u32,
+        rng: &mut Rng,
+    ) -> Option<UtilityForAllPlayers> {
+        self.condition
+            .is_leaf(gamestate, depth)
+            .then(|| self.evaluator.evaluate(gamestate, rng))
+    }
+}
+
+/// Averages the utility of games played out with uniformly random moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandomRollout {
+    rollouts: u32,
+}
+
+impl RandomRollout {
+    /// Needs at least one rollout to average over
+    pub fn new(rollouts: u32) -> Self {
+        assert!(rollouts > 0, "random rollouts need at least one rollout");
+        Self { rollouts }
+    }
+}
+
+impl<INFO: VisibleInfo> LeafEvaluator<INFO> for RandomRollout {
+    fn evaluate(&self, gamestate: &INFO::Gamestate, rng: &mut Rng) -> UtilityForAllPlayers {
+        let mut total = UtilityForAllPlayers::default();
+        for _ in 0..self.rollouts {
+            total.accumulate(&random_rollout::<INFO>(gamestate.clone(), rng), 1.0);
+        }
+        total.reduce(1.0 / self.rollouts as f64);
+
+        total
+    }
+}
+
+fn random_rollout<INFO: VisibleInfo>(
+    mut gamestate: INFO::Gamestate,
+    rng: &mut Rng,
+) -> UtilityForAllPlayers {
+    let mut moves = Vec::new();
+
+    loop {
+        if let Some(mut outcomes) = gamestate.chance_outcomes() {
+            let mark = rng.f64();
+            let mut cumulative = 0.0;
+            let i = outcomes
+                .iter()
+                .position(|(_, p)| {
+                    cumulative += p;
+                    mark < cumulative
+                })
+                .unwrap_or(outcomes.len() - 1);
+            gamestate = outcomes.swap_remove(i).0;
+            continue;
+        }
+
+        moves.clear();
+        if let Some(utility) = gamestate
+            .info_for_turn_player()
+            .run_for_moves(|m| moves.push(m))
+        {
+            return utility;
+        }
+
+        gamestate = gamestate.advance(&moves[rng.usize(0..moves.len())]);
+    }
+}
+
+/// Plays the rest of the game as if everyone could see every card, with each player picking the
+/// move that's best for themselves
+///
+/// Results are remembered for the lifetime of the evaluator, so it gets faster as training goes
+/// on, at the cost of memory.
+pub struct PerfectInformationRollout<INFO: VisibleInfo> {
+    known: DashMap<INFO::Gamestate, UtilityForAllPlayers>,
+}
+
+impl<INFO: VisibleInfo> Default for PerfectInformationRollout<INFO> {
+    fn default() -> Self {
+        Self {
+            known: DashMap::new(),
+        }
+    }
+}
+
+impl<INFO: VisibleInfo> LeafEvaluator<INFO> for PerfectInformationRollout<INFO> {
+    fn evaluate(&self, gamestate: &INFO::Gamestate, _rng: &mut Rng) -> UtilityForAllPlayers {
+        self.best_utility(gamestate)
+    }
+}
+
+impl<INFO: VisibleInfo> PerfectInformationRollout<INFO> {
+    fn best_utility(&self, gamestate: &INFO::Gamestate) -> UtilityForAllPlayers {
+        if let Some(utility) = self.known.get(gamestate) {
+            return *utility;
+        }
+
+        let utility = if let Some(outcomes) = gamestate.chance_outcomes() {
+            let mut utility = UtilityForAllPlayers::default();
+            for (outcome, p) in &outcomes {
+                utility.accumulate(&self.best_utility(outcome), *p);
+            }
+            utility
+        } else {
+            let turn = gamestate.turn();
+            let mut moves = Vec::new();
+            match gamestate
+                .info_for_turn_player()
+                .run_for_moves(|m| moves.push(m))
+            {
+                Some(utility) => utility,
+                None => moves
+                    .iter()
+                    .map(|m| self.best_utility(&gamestate.advance(m)))
+                    .max_by(|a, b| a.get(turn).total_cmp(&b.get(turn)))
+                    .expect("Non-terminal gamestates have moves"),
+            }
+        };
+        self.known.insert(gamestate.clone(), utility);
+
+        utility
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::depth_limit::{
+        DepthLimit, MaxDepth, PerfectInformationRollout, RandomRollout,
+    };
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::Traversal;
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler, TicTacToeSquare};
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn depth_limited_traversals_stop_at_the_limit() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_traversal(Traversal::Full)
+            .with_depth_limit(DepthLimit::new(
+                MaxDepth(2),
+                PerfectInformationRollout::default(),
+            ));
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: TicTacToeBoard::default(),
+            },
+            2,
+        );
+
+        let mut deepest = 0;
+        strategy_generator
+            .into_strategy()
+            .infosets
+            .for_each(|board, _| {
+                let moves_played = board
+                    .squares
+                    .iter()
+                    .filter(|square| **square != TicTacToeSquare::Empty)
+                    .count();
+                deepest = deepest.max(moves_played);
+            });
+        // Gamestates two moves in are leaves, so they never get an infoset
+        assert_eq!(deepest, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "random rollouts need at least one rollout")]
+    fn random_rollouts_need_at_least_one() {
+        RandomRollout::new(0);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::cfr::game_model::{PlayerNumber, Probability, Utility, VisibleInfo};
+use crate::cfr::strategy_generation::observer::json_number;
+use crate::cfr::strategy_generation::strategy::{policy_probabilities, Policy};
+use crate::cfr::strategy_generation::workspace_data::data_for_known_infosets::DataForKnownInfosets;
+use std::fmt::Write as _;
+use std::io;
+use std::io::Write;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// An array with an object per record
+    #[default]
+    Json,
+    /// A header line, then a line per record
+    Csv,
+}
+
+/// Writes out a strategy for inspecting it elsewhere, such as in a notebook
+///
+/// There's a record for each move of each infoset, with the infoset and move as their `Debug`
+/// renderings, whose turn it is, how many times traversals reached the infoset, the move's
+/// current and average probability and its cumulative regret. Terminal infosets get a single
+/// record, without a move.
+///
+/// Infosets come out in no particular order.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StrategyExport {
+    pub format: ExportFormat,
+    /// Only export infosets where it's this player's turn
+    pub turn_player: Option<PlayerNumber>,
+    /// Only export infosets that traversals reached at least this many times
+    pub min_visits: u64,
+}
+
+struct Record<'a> {
+    infoset: &'a str,
+    turn_player: PlayerNumber,
+    terminal: bool,
+    visits: u64,
+    // Terminal infosets have no move
+    m: Option<MoveRecord<'a>>,
+}
+
+struct MoveRecord<'a> {
+    m: &'a str,
+    current_probability: Probability,
+    average_probability: Probability,
+    cumulative_regret: Utility,
+}
+
+const CSV_HEADER: &str = "infoset,turn_player,terminal,visits,move,current_probability,\
+                          average_probability,cumulative_regret";
+
+impl StrategyExport {
+    pub(crate) fn write<INFO: VisibleInfo>(
+        &self,
+        infosets: &DataForKnownInfosets<INFO>,
+        w: &mut impl Write,
+    ) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Json => w.write_all(b"[")?,
+            ExportFormat::Csv => writeln!(w, "{CSV_HEADER}")?,
+        }
+
+        let mut first = true;
+        let mut line = String::new();
+        infosets.try_for_each(|info, data| {
+            if self.turn_player.is_some_and(|turn| turn != data.turn())
+                || data.visits() < self.min_visits
+            {
+                return Ok(());
+            }
+
+            let infoset = format!("{info:?}");
+            let mut write_record = |m: Option<MoveRecord>| {
+                line.clear();
+                let record = Record {
+                    infoset: &infoset,
+                    turn_player: data.turn(),
+                    terminal: data.is_terminal(),
+                    visits: data.visits(),
+                    m,
+                };
+                match self.format {
+                    ExportFormat::Json => {
+                        line.push_str(if first { "\n" } else { ",\n" });
+                        record.push_json(&mut line);
+                    }
+                    ExportFormat::Csv => {
+                        record.push_csv(&mut line);
+                        line.push('\n');
+                    }
+                }
+                first = false;
+
+                w.write_all(line.as_bytes())
+            };
+
+            if data.is_terminal() {
+                return write_record(None);
+            }
+
+            let averages = policy_probabilities(data, Policy::Average);
+            let currents = policy_probabilities(data, Policy::Current);
+            for ((move_with_data, (_, average)), (_, current)) in
+                data.moves().iter().zip(averages).zip(currents)
+            {
+                write_record(Some(MoveRecord {
+                    m: &format!("{:?}", move_with_data.m),
+                    current_probability: current,
+                    average_probability: average,
+                    cumulative_regret: move_with_data.d.regret(),
+                }))?;
+            }
+
+            Ok(())
+        })?;
+
+        match self.format {
+            ExportFormat::Json => w.write_all(if first { b"]\n" } else { b"\n]\n" }),
+            ExportFormat::Csv => Ok(()),
+        }
+    }
+}
+
+impl Record<'_> {
+    fn push_json(&self, out: &mut String) {
+        out.push_str("{\"infoset\":");
+        push_json_string(out, self.infoset);
+        let _ = write!(
+            out,
+            ",\"turn_player\":{},\"terminal\":{},\"visits\":{},\"move\":",
+            self.turn_player, self.terminal, self.visits
+        );
+
+        match &self.m {
+            Some(m) => {
+                push_json_string(out, m.m);
+                let _ = write!(
+                    out,
+                    ",\"current_probability\":{},\"average_probability\":{},\
+                     \"cumulative_regret\":{}}}",
+                    json_number(m.current_probability),
+                    json_number(m.average_probability),
+                    json_number(m.cumulative_regret),
+                );
+            }
+            None => out.push_str(
+                "null,\"current_probability\":null,\"average_probability\":null,\
+                 \"cumulative_regret\":null}",
+            ),
+        }
+    }
+
+    fn push_csv(&self, out: &mut String) {
+        push_csv_field(out, self.infoset);
+        let _ = write!(
+            out,
+            ",{},{},{},",
+            self.turn_player, self.terminal, self.visits
+        );
+
+        if let Some(m) = &self.m {
+            push_csv_field(out, m.m);
+            let _ = write!(
+                out,
+                ",{},{},{}",
+                m.current_probability, m.average_probability, m.cumulative_regret
+            );
+        } else {
+            out.push_str(",,,");
+        }
+    }
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Quotes `s` if it has anything that would otherwise break up the line
+fn push_csv_field(out: &mut String, s: &str) {
+    if s.contains([',', '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&s.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(s);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::export::{
+        push_csv_field, push_json_string, ExportFormat, StrategyExport,
+    };
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn fields_are_escaped() {
+        let mut json = String::new();
+        push_json_string(&mut json, "a \"b\"\n\\");
+        assert_eq!(json, r#""a \"b\"\n\\""#);
+
+        let mut csv = String::new();
+        push_csv_field(&mut csv, "plain");
+        csv.push(',');
+        push_csv_field(&mut csv, "a, \"b\"");
+        assert_eq!(csv, r#"plain,"a, ""b""""#);
+    }
+
+    #[test]
+    fn exports_filtered_records() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board_with_win_for_x(),
+            },
+            20,
+        );
+
+        let mut csv = Vec::new();
+        let export = StrategyExport {
+            format: ExportFormat::Csv,
+            turn_player: Some(1),
+            min_visits: 2,
+        };
+        strategy_generator.export(&export, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        let mut lines = csv.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("infoset,turn_player,terminal,visits,move"));
+        let records: Vec<Vec<String>> = lines
+            .map(|line| {
+                // The infoset and move have commas of their own, so they're quoted
+                let mut fields = vec![String::new()];
+                let mut quoted = false;
+                for c in line.chars() {
+                    match c {
+                        '"' => quoted = !quoted,
+                        ',' if !quoted => fields.push(String::new()),
+                        c => fields.last_mut().unwrap().push(c),
+                    }
+                }
+                fields
+            })
+            .collect();
+        assert!(!records.is_empty());
+        for record in records {
+            assert_eq!(record.len(), 8);
+            assert_eq!(record[1], "1");
+            assert!(record[3].parse::<u64>().unwrap() >= 2);
+        }
+
+        let mut json = Vec::new();
+        strategy_generator
+            .export(&StrategyExport::default(), &mut json)
+            .unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("[\n{\"infoset\":\"TicTacToeBoard"));
+        assert!(json.ends_with("}\n]\n"));
+        assert!(json.contains("\"terminal\":true,\"visits\":"));
+    }
+}
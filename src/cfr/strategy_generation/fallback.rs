@@ -0,0 +1,441 @@
+use crate::cfr::game_model::{
+    GamestateSampler, OracleGamestate, Probability, Utility, VisibleInfo,
+};
+use crate::cfr::strategy_generation::depth_limit::LeafEvaluator;
+use crate::cfr::strategy_generation::strategy::{StrategyForInfoView, StrategySource};
+use fastrand::Rng;
+use rustc_hash::FxHashMap;
+
+/// The probability of each move at an infoset
+pub type MoveProbabilities<INFO> = Vec<(<INFO as VisibleInfo>::Move, Probability)>;
+
+/// Looks up what training learnt for an infoset, if it reached it
+pub type TrainedLookup<'a, INFO> = &'a dyn Fn(&INFO) -> Option<MoveProbabilities<INFO>>;
+
+/// What to play at an infoset training never reached, instead of picking uniformly at random
+///
+/// Fallbacks are asked in the order they were added, until one of them gives an answer.
+pub trait FallbackPolicy<INFO: VisibleInfo>: Send + Sync {
+    /// Reported by [`StrategyForInfoView::source`] when this fallback gave the probabilities
+    fn source(&self) -> StrategySource;
+
+    /// The probability of each move at `info`, or `None` to leave it to the next fallback
+    ///
+    /// Moves that aren't legal at `info` are dropped, and the rest are scaled to add up to 1.
+    /// Any randomness should come from `rng`, so games can be reproduced.
+    fn move_probabilities(
+        &self,
+        info: &INFO,
+        trained: TrainedLookup<INFO>,
+        rng: &mut Rng,
+    ) -> Option<MoveProbabilities<INFO>>;
+}
+
+/// Plays like the nearest infoset training did reach, such as the same hand with a small card
+/// swapped for another
+///
+/// `neighbours` lists the infosets to try, nearest first. Only their moves that are also legal at
+/// the original infoset count, and the first neighbour with any of those gets used.
+pub struct NearestInfoset<F> {
+    pub neighbours: F,
+}
+
+impl<INFO: VisibleInfo, F: Fn(&INFO) -> Vec<INFO> + Send + Sync> FallbackPolicy<INFO>
+    for NearestInfoset<F>
+{
+    fn source(&self) -> StrategySource {
+        StrategySource::NearestInfoset
+    }
+
+    fn move_probabilities(
+        &self,
+        info: &INFO,
+        trained: TrainedLookup<INFO>,
+        _rng: &mut Rng,
+    ) -> Option<MoveProbabilities<INFO>> {
+        let legal = legal_moves(info);
+
+        (self.neighbours)(info)
+            .iter()
+            .filter_map(trained)
+            .map(|probabilities| restrict_to::<INFO>(&legal, probabilities))
+            .find(|probabilities| !probabilities.is_empty())
+    }
+}
+
+/// Samples worlds the infoset could be in, and plays the move that does best on average once
+/// they're played out by `evaluator`
+///
+/// With [`PerfectInformationRollout`](crate::cfr::strategy_generation::depth_limit::PerfectInformationRollout)
+/// this is double dummy play, which at least never throws away a winner. Moves that tie for best
+/// are equally likely.
+pub struct SampledRollout<F, E> {
+    pub worlds: usize,
+    /// Gives a sampler for the worlds that are consistent with an infoset
+    pub sampler_for: F,
+    pub evaluator: E,
+}
+
+impl<INFO, SAMPLER, F, E> FallbackPolicy<INFO> for SampledRollout<F, E>
+where
+    INFO: VisibleInfo,
+    SAMPLER: GamestateSampler<Info = INFO>,
+    F: Fn(&INFO) -> SAMPLER + Send + Sync,
+    E: LeafEvaluator<INFO>,
+{
+    fn source(&self) -> StrategySource {
+        StrategySource::Rollout
+    }
+
+    fn move_probabilities(
+        &self,
+        info: &INFO,
+        _trained: TrainedLookup<INFO>,
+        rng: &mut Rng,
+    ) -> Option<MoveProbabilities<INFO>> {
+        let legal = legal_moves(info);
+        let turn = info.turn();
+
+        let mut sampler = (self.sampler_for)(info);
+        let mut totals: Vec<Utility> = vec![0.0; legal.len()];
+        let mut total_weight = 0.0;
+        for _ in 0..self.worlds {
+            let (world, probability) = sampler.sample(rng);
+            // Worlds the sampler draws more often than they come up count for less
+            let weight = probability / sampler.sampling_probability(&world, probability);
+            total_weight += weight;
+
+            for (m, total) in legal.iter().zip(&mut totals) {
+                *total += weight * self.evaluator.evaluate(&world.advance(m), rng).get(turn);
+            }
+        }
+
+        if total_weight <= 0.0 || !total_weight.is_finite() {
+            return None;
+        }
+
+        let best = totals
+            .iter()
+            .copied()
+            .fold(Utility::NEG_INFINITY, Utility::max);
+        // Totals only a rounding error apart, from adding up the same utilities in a different
+        // order, still count as tied
+        let is_best = |total: Utility| total >= best - TIE_TOLERANCE * best.abs().max(1.0);
+        let n_best = totals.iter().filter(|total| is_best(**total)).count();
+
+        Some(
+            legal
+                .into_iter()
+                .zip(totals)
+                .map(|(m, total)| {
+                    let probability = if is_best(total) {
+                        1.0 / n_best as Probability
+                    } else {
+                        0.0
+                    };
+                    (m, probability)
+                })
+                .collect(),
+        )
+    }
+}
+
+const TIE_TOLERANCE: Utility = 1e-9;
+
+/// Asks `heuristic` for the probabilities, such as a rule of thumb like second hand low
+pub struct Heuristic<F> {
+    pub heuristic: F,
+}
+
+impl<INFO: VisibleInfo, F: Fn(&INFO) -> Option<MoveProbabilities<INFO>> + Send + Sync>
+    FallbackPolicy<INFO> for Heuristic<F>
+{
+    fn source(&self) -> StrategySource {
+        StrategySource::Heuristic
+    }
+
+    fn move_probabilities(
+        &self,
+        info: &INFO,
+        _trained: TrainedLookup<INFO>,
+        _rng: &mut Rng,
+    ) -> Option<MoveProbabilities<INFO>> {
+        (self.heuristic)(info)
+    }
+}
+
+/// The strategy at `info`, which training never reached, from the first of `fallbacks` with an
+/// answer, or uniform if none have one
+pub(crate) fn fall_back<'h, INFO: VisibleInfo>(
+    fallbacks: &[Box<dyn FallbackPolicy<INFO>>],
+    info: &INFO,
+    trained: TrainedLookup<INFO>,
+    rng: &mut Rng,
+) -> StrategyForInfoView<'h, INFO> {
+    let legal = legal_moves(info);
+    if legal.is_empty() {
+        return uniform(info);
+    }
+
+    for fallback in fallbacks {
+        let Some(probabilities) = fallback.move_probabilities(info, trained, rng) else {
+            continue;
+        };
+
+        let probabilities = restrict_to::<INFO>(&legal, probabilities);
+        if !probabilities.is_empty() {
+            return StrategyForInfoView::untrained(
+                probabilities.into_iter().collect(),
+                false,
+                fallback.source(),
+            );
+        }
+    }
+
+    uniform(info)
+}
+
+/// Every move at `info` equally likely, for when there's nothing better to go on
+pub(crate) fn uniform<'h, INFO: VisibleInfo>(info: &INFO) -> StrategyForInfoView<'h, INFO> {
+    let mut legal = Vec::new();
+    if info.run_for_moves(|m| legal.push(m)).is_some() {
+        return StrategyForInfoView::untrained(FxHashMap::default(), true, StrategySource::Uniform);
+    }
+
+    let probability = 1.0 / legal.len() as Probability;
+    StrategyForInfoView::untrained(
+        legal.into_iter().map(|m| (m, probability)).collect(),
+        false,
+        StrategySource::Uniform,
+    )
+}
+
+fn legal_moves<INFO: VisibleInfo>(info: &INFO) -> Vec<INFO::Move> {
+    let mut legal = Vec::new();
+    info.run_for_moves(|m| legal.push(m));
+    legal
+}
+
+/// Gives every move in `legal` its probability from `probabilities`, scaled to add up to 1, or
+/// nothing if none of them have any
+pub(crate) fn restrict_to<INFO: VisibleInfo>(
+    legal: &[INFO::Move],
+    probabilities: MoveProbabilities<INFO>,
+) -> MoveProbabilities<INFO> {
+    let restricted: MoveProbabilities<INFO> = legal
+        .iter()
+        .map(|m| {
+            let probability = probabilities
+                .iter()
+                .filter(|(other, _)| other == m)
+                .map(|(_, p)| p.max(0.0))
+                .sum();
+            (*m, probability)
+        })
+        .collect();
+
+    let total: Probability = restricted.iter().map(|(_, p)| p).sum();
+    if total <= 0.0 || !total.is_finite() {
+        return Vec::new();
+    }
+
+    restricted
+        .into_iter()
+        .map(|(m, p)| (m, p / total))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::game_model::{
+        GamestateSampler, OracleGamestate, PlayerNumber, Probability, UtilityForAllPlayers,
+        VisibleInfo,
+    };
+    use crate::cfr::strategy_generation::depth_limit::{LeafEvaluator, PerfectInformationRollout};
+    use crate::cfr::strategy_generation::fallback::{
+        FallbackPolicy, Heuristic, NearestInfoset, SampledRollout,
+    };
+    use crate::cfr::strategy_generation::strategy::{Policy, StrategySource};
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::{board_with_win_for_x, WINNING_MOVE};
+    use crate::tic_tac_toe::{
+        Player, TicTacToeBoard, TicTacToeMove, TicTacToeSampler, TicTacToeSquare,
+    };
+    use bumpalo_herd::Herd;
+    use fastrand::Rng;
+
+    /// Player 0 picks a move in one of three worlds, then the game is over
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum ThreeWorlds {
+        Pick { world: u8 },
+        Picked { world: u8, first: bool },
+    }
+
+    impl OracleGamestate<ThreeWorlds> for ThreeWorlds {
+        fn info_for_turn_player(&self) -> ThreeWorlds {
+            *self
+        }
+
+        fn info_for_player(&self, _player: PlayerNumber) -> ThreeWorlds {
+            *self
+        }
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn advance(&self, first: &bool) -> Self {
+            match self {
+                ThreeWorlds::Pick { world } => ThreeWorlds::Picked {
+                    world: *world,
+                    first: *first,
+                },
+                ThreeWorlds::Picked { .. } => unreachable!(),
+            }
+        }
+    }
+
+    impl VisibleInfo for ThreeWorlds {
+        type Move = bool;
+        type Gamestate = ThreeWorlds;
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn run_for_moves(&self, mut f: impl FnMut(bool)) -> Option<UtilityForAllPlayers> {
+            f(true);
+            f(false);
+            None
+        }
+    }
+
+    /// Gives every world in turn
+    #[derive(Debug, Clone)]
+    struct EachWorld(u8);
+
+    impl GamestateSampler for EachWorld {
+        type Info = ThreeWorlds;
+
+        fn sample(&mut self, _rng: &mut Rng) -> (ThreeWorlds, Probability) {
+            let world = self.0;
+            self.0 = (self.0 + 1) % 3;
+            (ThreeWorlds::Pick { world }, 1.0)
+        }
+    }
+
+    /// The first move is worth 0.1 in every world, and the second 0.3 in only one of them. Both
+    /// add up to 0.3, but not exactly, since 0.1 + 0.1 + 0.1 rounds above 0.3
+    struct Payouts;
+
+    impl LeafEvaluator<ThreeWorlds> for Payouts {
+        fn evaluate(&self, gamestate: &ThreeWorlds, _rng: &mut Rng) -> UtilityForAllPlayers {
+            let payout = match gamestate {
+                ThreeWorlds::Picked { first: true, .. } => 0.1,
+                ThreeWorlds::Picked { world: 0, .. } => 0.3,
+                _ => 0.0,
+            };
+            UtilityForAllPlayers {
+                util: [payout, 0.0, 0.0, 0.0],
+            }
+        }
+    }
+
+    #[test]
+    fn rollouts_tied_up_to_rounding_are_equally_likely() {
+        assert_ne!(0.1 + 0.1 + 0.1, 0.3);
+
+        let rollout = SampledRollout {
+            worlds: 3,
+            sampler_for: |_: &ThreeWorlds| EachWorld(0),
+            evaluator: Payouts,
+        };
+        let probabilities = rollout
+            .move_probabilities(&ThreeWorlds::Pick { world: 0 }, &|_| None, &mut Rng::new())
+            .unwrap();
+
+        assert_eq!(probabilities, vec![(true, 0.5), (false, 0.5)]);
+    }
+
+    #[test]
+    fn falling_back_for_unseen_infosets() {
+        let mut rng = Rng::with_seed(0);
+        let o_to_move = TicTacToeBoard::default().advance(&TicTacToeMove {
+            state: TicTacToeSquare::X,
+            square: 4,
+        });
+        let o_in_the_corner = TicTacToeMove {
+            state: TicTacToeSquare::O,
+            square: 8,
+        };
+
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_fallback(Heuristic {
+                heuristic: move |info: &TicTacToeBoard| {
+                    (info.turn == Player::O).then(|| vec![(o_in_the_corner, 1.0)])
+                },
+            })
+            .with_fallback(SampledRollout {
+                worlds: 1,
+                sampler_for: |info: &TicTacToeBoard| TicTacToeSampler {
+                    board: info.clone(),
+                },
+                evaluator: PerfectInformationRollout::default(),
+            });
+
+        let heuristic =
+            strategy_generator.strategy_for_info_or_fallback(&o_to_move, Policy::Average, &mut rng);
+        assert_eq!(heuristic.source(), StrategySource::Heuristic);
+        assert_eq!(heuristic.move_probability(&o_in_the_corner), 1.0);
+
+        let rollout = strategy_generator.strategy_for_info_or_fallback(
+            &board_with_win_for_x(),
+            Policy::Average,
+            &mut rng,
+        );
+        assert_eq!(rollout.source(), StrategySource::Rollout);
+        assert!(rollout.move_probability(&WINNING_MOVE) > 0.0);
+
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd).with_fallback(NearestInfoset {
+            neighbours: |_: &TicTacToeBoard| vec![board_with_win_for_x()],
+        });
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board_with_win_for_x(),
+            },
+            200,
+        );
+
+        let trained = strategy_generator.strategy_for_info_or_fallback(
+            &board_with_win_for_x(),
+            Policy::Average,
+            &mut rng,
+        );
+        assert_eq!(trained.source(), StrategySource::Trained);
+
+        let strategy = strategy_generator.into_strategy();
+        let nearest = strategy.move_probabilities_or_fallback(
+            &TicTacToeBoard::default(),
+            Policy::Average,
+            &mut rng,
+        );
+        assert_eq!(nearest.source(), StrategySource::NearestInfoset);
+        assert!(nearest.move_probability(&WINNING_MOVE) > 0.9);
+
+        // None of the neighbour's moves are legal for O
+        let uniform =
+            strategy.move_probabilities_or_fallback(&o_to_move, Policy::Average, &mut rng);
+        assert_eq!(uniform.source(), StrategySource::Uniform);
+        assert_eq!(uniform.move_probability(&o_in_the_corner), 1.0 / 8.0);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::cfr::game_model::{invalid_data, Encode, Probability, VisibleInfo};
+use crate::cfr::strategy_generation::strategy::{policy_probabilities, Policy};
+use crate::cfr::strategy_generation::workspace_data::data_for_known_infosets::DataForKnownInfosets;
+use fastrand::Rng;
+use rustc_hash::FxHashMap;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::io::{Read, Write};
+
+const MAGIC: [u8; 8] = *b"HAWTHSTR";
+const VERSION: u32 = 1;
+
+// Layout, after the magic and version:
+//     infoset count: u64
+//     per infoset:
+//         info: INFO
+//         move count: u32
+//         per move:
+//             move: INFO::Move
+//             probability: f32
+
+type FrozenMoves<INFO> = Box<[(<INFO as VisibleInfo>::Move, Probability)]>;
+
+/// A finished strategy, for playing rather than training
+///
+/// Only the probability of each move is kept, without any regret, and it's all owned, so it can
+/// be shared between threads without a [`Herd`](bumpalo_herd::Herd). Lookups never allocate.
+/// [`Self::write`] and [`Self::read`] move it between processes, with the probabilities stored to
+/// single precision.
+///
+/// Terminal infosets are left out, since there's nothing to play there.
+pub struct FrozenStrategy<INFO: VisibleInfo> {
+    infosets: FxHashMap<INFO, FrozenMoves<INFO>>,
+}
+
+impl<INFO: VisibleInfo> FrozenStrategy<INFO> {
+    pub(crate) fn from_infosets(infosets: &DataForKnownInfosets<INFO>, policy: Policy) -> Self
+    where
+        INFO: Clone,
+    {
+        let mut frozen = FxHashMap::default();
+        infosets.for_each(|info, data| {
+            if !data.is_terminal() {
+                frozen.insert(info.clone(), policy_probabilities(data, policy).collect());
+            }
+        });
+
+        Self { infosets: frozen }
+    }
+
+    /// The probability of each move at `info`, or `None` if training never reached it
+    pub fn get(&self, info: &INFO) -> Option<&[(INFO::Move, Probability)]> {
+        self.infosets.get(info).map(|moves| &**moves)
+    }
+
+    pub fn move_probability(&self, info: &INFO, m: &INFO::Move) -> Option<Probability> {
+        self.get(info)?
+            .iter()
+            .find(|(other, _)| other == m)
+            .map(|(_, probability)| *probability)
+    }
+
+    /// Picks a move to play at `info`, or `None` if training never reached it
+    pub fn pick_move(&self, info: &INFO, rng: &mut Rng) -> Option<INFO::Move> {
+        let moves = self.get(info)?;
+
+        let mark = rng.f64();
+        let mut cumulative = 0.0;
+        for (m, probability) in moves {
+            cumulative += probability;
+
+            if mark < cumulative {
+                return Some(*m);
+            }
+        }
+
+        // The probabilities can sum to a little under 1 after rounding
+        moves
+            .iter()
+            .rev()
+            .find(|(_, probability)| *probability > 0.0)
+            .map(|(m, _)| *m)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&INFO, &[(INFO::Move, Probability)])> {
+        self.infosets.iter().map(|(info, moves)| (info, &**moves))
+    }
+
+    pub fn len(&self) -> usize {
+        self.infosets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.infosets.is_empty()
+    }
+}
+
+impl<INFO: VisibleInfo + Encode> FrozenStrategy<INFO>
+where
+    INFO::Move: Encode,
+{
+    /// Writes this in a versioned binary format that [`Self::read`] loads. This writes lots of
+    /// small pieces, so `w` is best buffered
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(&MAGIC)?;
+        VERSION.encode(&mut w)?;
+
+        self.infosets.len().encode(&mut w)?;
+        for (info, moves) in &self.infosets {
+            info.encode(&mut w)?;
+
+            (moves.len() as u32).encode(&mut w)?;
+            for (m, probability) in moves {
+                m.encode(&mut w)?;
+                (*probability as f32).encode(&mut w)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read(mut r: impl Read) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a frozen strategy"));
+        }
+        if u32::decode(&mut r)? != VERSION {
+            return Err(invalid_data("unsupported frozen strategy version"));
+        }
+
+        let n_infosets = usize::decode(&mut r)?;
+        // The count can't be trusted until that many infosets have actually been read
+        let mut infosets = FxHashMap::default();
+        infosets.reserve(n_infosets.min(1 << 16));
+
+        for _ in 0..n_infosets {
+            let info = INFO::decode(&mut r)?;
+
+            let moves = (0..u32::decode(&mut r)?)
+                .map(|_| {
+                    Ok((
+                        INFO::Move::decode(&mut r)?,
+                        f32::decode(&mut r)? as Probability,
+                    ))
+                })
+                .collect::<io::Result<_>>()?;
+
+            if infosets.insert(info, moves).is_some() {
+                return Err(invalid_data("infoset appears twice"));
+            }
+        }
+
+        Ok(Self { infosets })
+    }
+}
+
+impl<INFO: VisibleInfo> Debug for FrozenStrategy<INFO> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrozenStrategy")
+            .field("infosets", &self.infosets.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::frozen_strategy::FrozenStrategy;
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn frozen_strategies_round_trip() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board_with_win_for_x(),
+            },
+            20,
+        );
+
+        let strategy = strategy_generator.into_strategy();
+        let frozen = strategy.freeze(Policy::Average);
+        let mut bytes = Vec::new();
+        frozen.write(&mut bytes).unwrap();
+        let loaded = FrozenStrategy::<TicTacToeBoard>::read(bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.len(), frozen.len());
+        for (info, moves) in frozen.iter() {
+            let trained = strategy
+                .lookup_move_probabilities(info, Policy::Average)
+                .unwrap();
+            for (m, probability) in moves {
+                assert_eq!(*probability, trained.move_probability(m));
+                let loaded_probability = loaded.move_probability(info, m).unwrap();
+                assert!((loaded_probability - probability).abs() < 1e-6);
+            }
+        }
+
+        // Only what training reached gets frozen
+        assert_eq!(loaded.get(&TicTacToeBoard::default()), None);
+    }
+}
@@ -1,11 +1,23 @@
+pub mod averaging;
 mod cfr_algorithm_impl;
-mod strategy;
+pub mod cfr_config;
+mod checkpoint;
+pub mod depth_limit;
+pub mod export;
+pub mod fallback;
+pub mod frozen_strategy;
+pub mod observer;
+pub mod pruning;
+pub mod stop_condition;
+pub mod strategy;
 pub mod strategy_generator;
+pub mod traversal;
+pub mod traverser_schedule;
 mod update_strategy;
+pub mod warm_start;
 mod workspace_data;
+pub mod world_sampling;
 
 // FIXME: Next steps
-//     1. Abstract out to a struct so the strategy can be refined as the game advances
-//     2. Abstract out configuration decisions out to a type parameter
-//     3. Fix bridge infoset abstraction
-//     4. Cleanup code
+//     1. Fix bridge infoset abstraction
+//     2. Cleanup code
@@ -0,0 +1,204 @@
+use crate::cfr::game_model::{Probability, Utility};
+use crate::cfr::strategy_evaluation::Exploitability;
+use parking_lot::Mutex;
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What happened over a single training iteration
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IterationStats {
+    pub iteration: u32,
+    /// Wall time spent traversing the game tree and accumulating regret
+    pub traversal_time: Duration,
+    /// Wall time spent turning regret into the next strategy
+    pub update_time: Duration,
+    /// Infosets reached for the first time
+    pub infosets_created: usize,
+    /// Infosets whose strategy got updated
+    pub infosets_updated: usize,
+    /// Positive cumulative regret added up over every move of every updated infoset
+    pub total_positive_regret: Utility,
+    /// How far the strategy moved, as the L1 distance between the old and new strategy of each
+    /// updated infoset, averaged over them
+    pub average_strategy_change: Probability,
+    /// The most gamestates waiting on any workstack at once
+    pub peak_workstack_depth: usize,
+    /// Moves skipped by regret-based pruning, counted each time a traversal skipped one
+    pub moves_pruned: usize,
+    /// How exploitable the strategy was after this iteration, on the iterations it was
+    /// evaluated, see [`StrategyGenerator::with_exploitability_every`]
+    ///
+    /// [`StrategyGenerator::with_exploitability_every`]: crate::cfr::strategy_generation::strategy_generator::StrategyGenerator::with_exploitability_every
+    pub exploitability: Option<Exploitability>,
+}
+
+/// Gets told about training as it happens, such as to log or plot it
+pub trait TrainingObserver: Send + Sync {
+    fn on_iteration(&self, stats: &IterationStats);
+}
+
+impl<T: TrainingObserver + ?Sized> TrainingObserver for Arc<T> {
+    fn on_iteration(&self, stats: &IterationStats) {
+        (**self).on_iteration(stats)
+    }
+}
+
+/// Writes the stats of every iteration as a line of JSON, for plotting training curves offline
+///
+/// Observers can't fail, so the first write error is kept, and nothing more is written after it.
+/// Keep an [`Arc`] to the writer to check for it with [`JsonLinesWriter::finish`].
+pub struct JsonLinesWriter<W: Write + Send> {
+    state: Mutex<(W, io::Result<()>)>,
+}
+
+impl<W: Write + Send> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            state: Mutex::new((writer, Ok(()))),
+        }
+    }
+
+    /// Flushes everything written so far, returning the first error hit along the way
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self.state.lock();
+        let (writer, result) = &mut *state;
+
+        std::mem::replace(result, Ok(())).and_then(|()| writer.flush())
+    }
+}
+
+impl<W: Write + Send> TrainingObserver for JsonLinesWriter<W> {
+    fn on_iteration(&self, stats: &IterationStats) {
+        let mut state = self.state.lock();
+        let (writer, result) = &mut *state;
+        if result.is_err() {
+            return;
+        }
+
+        *result = writeln!(
+            writer,
+            "{{\"iteration\":{},\"traversal_seconds\":{},\"update_seconds\":{},\
+             \"infosets_created\":{},\"infosets_updated\":{},\"total_positive_regret\":{},\
+             \"average_strategy_change\":{},\"peak_workstack_depth\":{},\"moves_pruned\":{},\
+             \"exploitability\":{}}}",
+            stats.iteration,
+            stats.traversal_time.as_secs_f64(),
+            stats.update_time.as_secs_f64(),
+            stats.infosets_created,
+            stats.infosets_updated,
+            json_number(stats.total_positive_regret),
+            json_number(stats.average_strategy_change),
+            stats.peak_workstack_depth,
+            stats.moves_pruned,
+            stats
+                .exploitability
+                .map_or("null".to_string(), |e| json_number(e.exploitability())),
+        );
+    }
+}
+
+/// JSON has no NaN or infinity, so those become null
+pub(crate) fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::cfr::strategy_generation::observer::{
+        IterationStats, JsonLinesWriter, TrainingObserver,
+    };
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    /// Keeps the stats of every iteration, for checking afterwards
+    #[derive(Default)]
+    pub(crate) struct CollectStats(pub(crate) Mutex<Vec<IterationStats>>);
+
+    impl TrainingObserver for CollectStats {
+        fn on_iteration(&self, stats: &IterationStats) {
+            self.0.lock().push(stats.clone());
+        }
+    }
+
+    #[test]
+    fn writes_a_line_per_iteration() {
+        let writer = JsonLinesWriter::new(Vec::new());
+        writer.on_iteration(&IterationStats {
+            iteration: 3,
+            infosets_updated: 7,
+            total_positive_regret: 1.5,
+            average_strategy_change: f64::NAN,
+            ..Default::default()
+        });
+        writer.on_iteration(&IterationStats::default());
+        writer.finish().unwrap();
+
+        let output = String::from_utf8(writer.state.into_inner().0).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "{\"iteration\":3,\"traversal_seconds\":0,\"update_seconds\":0,\
+             \"infosets_created\":0,\"infosets_updated\":7,\"total_positive_regret\":1.5,\
+             \"average_strategy_change\":null,\"peak_workstack_depth\":0,\"moves_pruned\":0,\
+             \"exploitability\":null}"
+        );
+    }
+
+    #[test]
+    fn observer_sees_exploitability_on_schedule() {
+        let herd = Herd::new();
+        let observer = Arc::new(CollectStats::default());
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_exploitability_every(sampler.clone(), Policy::Average, 5)
+            .with_observer(observer.clone());
+        strategy_generator.refine_strategy(sampler.clone(), 20);
+
+        let stats = observer.0.lock();
+        assert!(stats
+            .iter()
+            .all(|s| s.exploitability.is_some() == s.iteration.is_multiple_of(5)));
+
+        let evaluated: Vec<_> = stats.iter().filter_map(|s| s.exploitability).collect();
+        assert_eq!(evaluated.len(), 4);
+        assert_eq!(
+            evaluated[3],
+            strategy_generator.exploitability(&sampler, Policy::Average)
+        );
+        assert!(evaluated[3].exploitability() < evaluated[0].exploitability());
+    }
+
+    #[test]
+    fn observer_sees_every_iteration() {
+        let herd = Herd::new();
+        let observer = Arc::new(CollectStats::default());
+        let strategy_generator = StrategyGenerator::new(&herd).with_observer(observer.clone());
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board_with_win_for_x(),
+            },
+            10,
+        );
+
+        let stats = observer.0.lock();
+        assert_eq!(stats.len(), 10);
+        assert!(stats[0].infosets_created > 0);
+        assert!(stats.iter().all(|s| s.infosets_updated > 0));
+        assert!(stats.iter().all(|s| s.peak_workstack_depth > 0));
+        assert_eq!(stats[9].iteration, stats[0].iteration + 9);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::cfr::game_model::Utility;
+use crate::cfr::strategy_generation::workspace_data::data_for_move::DataForMove;
+
+/// Regret-based pruning: skip the subtrees of moves that have been hopeless for a while
+///
+/// Only moves the current strategy never plays get pruned, so the utility of the infoset they're
+/// in is unaffected. What's lost is updating the regret of the pruned move and of everything
+/// below it, which is why every so often an iteration is run with nothing pruned. Needs a
+/// [`CfrConfig`](crate::cfr::strategy_generation::cfr_config::CfrConfig) that lets cumulative
+/// regret go negative, since otherwise nothing ever falls below the threshold, so
+/// [`with_pruning`] panics when given a threshold of zero or less with one that doesn't.
+///
+/// [`with_pruning`]: crate::cfr::strategy_generation::strategy_generator::StrategyGenerator::with_pruning
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegretPruning {
+    /// Moves with cumulative regret below this are candidates for pruning
+    pub threshold: Utility,
+    /// How many strategy updates in a row a move's regret has to stay below `threshold` for
+    pub after_updates: u32,
+    /// Every this many iterations, nothing gets pruned so moves can recover. Can't be zero
+    pub recheck_every: u32,
+}
+
+impl RegretPruning {
+    pub(crate) fn prunes(
+        &self,
+        iteration: u32,
+        data_for_move: &DataForMove,
+        n_moves: usize,
+    ) -> bool {
+        !iteration.is_multiple_of(self.recheck_every)
+            && data_for_move.updates_below_threshold() >= self.after_updates as u64
+            && data_for_move.load_move_probability(n_moves) == 0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::cfr_config::DiscountedCfr;
+    use crate::cfr::strategy_generation::observer::test::CollectStats;
+    use crate::cfr::strategy_generation::pruning::RegretPruning;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::Traversal;
+    use crate::tic_tac_toe::test::board_with_x_in_the_middle;
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use bumpalo_herd::Herd;
+    use std::sync::Arc;
+
+    const PRUNING: RegretPruning = RegretPruning {
+        threshold: -0.1,
+        after_updates: 5,
+        recheck_every: 10,
+    };
+
+    #[test]
+    fn pruning_skips_hopeless_moves() {
+        let total_visits = |pruning: Option<RegretPruning>| {
+            let herd = Herd::new();
+            let observer = Arc::new(CollectStats::default());
+            let mut strategy_generator =
+                StrategyGenerator::with_config(&herd, DiscountedCfr::default())
+                    .with_traversal(Traversal::Full)
+                    .with_observer(observer.clone());
+            if let Some(pruning) = pruning {
+                strategy_generator = strategy_generator.with_pruning(pruning);
+            }
+            strategy_generator.refine_strategy(
+                TicTacToeSampler {
+                    board: board_with_x_in_the_middle(),
+                },
+                50,
+            );
+
+            let stats = observer.0.lock();
+            assert_eq!(stats.iter().any(|s| s.moves_pruned > 0), pruning.is_some());
+            assert!(stats
+                .iter()
+                .filter(|s| s.iteration.is_multiple_of(PRUNING.recheck_every))
+                .all(|s| s.moves_pruned == 0));
+
+            let mut visits = 0;
+            strategy_generator
+                .into_strategy()
+                .infosets
+                .for_each(|_, data| visits += data.visits());
+            visits
+        };
+
+        assert!(total_visits(Some(PRUNING)) < total_visits(None));
+    }
+
+    #[test]
+    #[should_panic(expected = "pruning needs a config that lets cumulative regret go negative")]
+    fn pruning_needs_negative_regret() {
+        let herd = Herd::new();
+        // Linear CFR clips instantaneous regret, so cumulative regret never goes below zero
+        StrategyGenerator::<TicTacToeBoard>::new(&herd).with_pruning(PRUNING);
+    }
+
+    #[test]
+    #[should_panic(expected = "pruned moves need to be rechecked at some point")]
+    fn pruning_needs_rechecks() {
+        let herd = Herd::new();
+        StrategyGenerator::<TicTacToeBoard, _>::with_config(&herd, DiscountedCfr::default())
+            .with_pruning(RegretPruning {
+                recheck_every: 0,
+                ..PRUNING
+            });
+    }
+}
@@ -0,0 +1,416 @@
+use crate::cfr::game_model::{EnumerableGamestateSampler, Probability, Utility, VisibleInfo};
+use crate::cfr::strategy_evaluation::{evaluate_strategy, Exploitability};
+use crate::cfr::strategy_generation::fallback::MoveProbabilities;
+use crate::cfr::strategy_generation::strategy::{Policy, StrategyForInfoView};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// When [`refine_strategy_until`] should stop refining
+///
+/// Conditions can be combined with [`StopCondition::or`] and [`StopCondition::and`].
+///
+/// [`refine_strategy_until`]: crate::cfr::strategy_generation::strategy_generator::StrategyGenerator::refine_strategy_until
+pub struct StopCondition<INFO: VisibleInfo> {
+    kind: StopKind<INFO>,
+}
+
+enum StopKind<INFO: VisibleInfo> {
+    Iterations(u32),
+    TimeBudget(Duration),
+    Converged {
+        info: INFO,
+        policy: Policy,
+        epsilon: Probability,
+        over_iterations: u32,
+    },
+    Exploitability {
+        evaluate: Box<EvaluateStrategy<INFO>>,
+        policy: Policy,
+        target: Utility,
+        check_every: u32,
+    },
+    Cancelled(CancellationToken),
+    Any(Vec<StopKind<INFO>>),
+    All(Vec<StopKind<INFO>>),
+}
+
+/// Evaluates a strategy, given as the probability of each move at each infoset
+pub(crate) type EvaluateStrategy<INFO> =
+    dyn Fn(&mut dyn FnMut(INFO) -> MoveProbabilities<INFO>) -> Exploitability + Send + Sync;
+
+/// Evaluates strategies over the worlds of `sampler`, see [`evaluate_strategy`]
+pub(crate) fn evaluate_over<SAMPLER>(sampler: SAMPLER) -> Box<EvaluateStrategy<SAMPLER::Info>>
+where
+    SAMPLER: EnumerableGamestateSampler + Sync + 'static,
+{
+    Box::new(move |strategy| evaluate_strategy(&sampler, strategy))
+}
+
+impl<INFO: VisibleInfo> StopCondition<INFO> {
+    /// Stop after `n` iterations
+    pub fn iterations(n: u32) -> Self {
+        Self {
+            kind: StopKind::Iterations(n),
+        }
+    }
+
+    /// Stop once `budget` has passed since refining started. The iteration running at the time
+    /// still finishes, so this can go over by up to an iteration
+    pub fn time_budget(budget: Duration) -> Self {
+        Self {
+            kind: StopKind::TimeBudget(budget),
+        }
+    }
+
+    /// Stop once no move probability at `info` has changed by `epsilon` or more in any of the
+    /// last `over_iterations` iterations
+    pub fn converged(
+        info: INFO,
+        policy: Policy,
+        epsilon: Probability,
+        over_iterations: u32,
+    ) -> Self {
+        Self {
+            kind: StopKind::Converged {
+                info,
+                policy,
+                epsilon,
+                over_iterations,
+            },
+        }
+    }
+
+    /// Stop once [`Exploitability::exploitability`] over the worlds of `sampler` is at most
+    /// `target`
+    ///
+    /// Evaluating walks the entire game tree, so it only happens every `check_every` iterations,
+    /// starting before the first one.
+    pub fn exploitability<SAMPLER: EnumerableGamestateSampler<Info = INFO> + Sync + 'static>(
+        sampler: SAMPLER,
+        policy: Policy,
+        target: Utility,
+        check_every: u32,
+    ) -> Self {
+        assert!(
+            check_every > 0,
+            "exploitability has to be checked at some point"
+        );
+
+        Self {
+            kind: StopKind::Exploitability {
+                evaluate: evaluate_over(sampler),
+                policy,
+                target,
+                check_every,
+            },
+        }
+    }
+
+    /// Stop once `token` gets cancelled, such as from another thread
+    pub fn cancelled(token: CancellationToken) -> Self {
+        Self {
+            kind: StopKind::Cancelled(token),
+        }
+    }
+
+    /// Stop as soon as either condition is met
+    pub fn or(self, other: Self) -> Self {
+        let kind = match (self.kind, other.kind) {
+            (StopKind::Any(mut any), StopKind::Any(other)) => {
+                any.extend(other);
+                StopKind::Any(any)
+            }
+            (StopKind::Any(mut any), other) => {
+                any.push(other);
+                StopKind::Any(any)
+            }
+            (kind, other) => StopKind::Any(vec![kind, other]),
+        };
+
+        Self { kind }
+    }
+
+    /// Stop once both conditions are met
+    pub fn and(self, other: Self) -> Self {
+        let kind = match (self.kind, other.kind) {
+            (StopKind::All(mut all), StopKind::All(other)) => {
+                all.extend(other);
+                StopKind::All(all)
+            }
+            (StopKind::All(mut all), other) => {
+                all.push(other);
+                StopKind::All(all)
+            }
+            (kind, other) => StopKind::All(vec![kind, other]),
+        };
+
+        Self { kind }
+    }
+}
+
+/// Lets refining be stopped from elsewhere, see [`StopCondition::cancelled`]
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Which condition refining stopped for. When several had to be met, it's the last one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Iterations,
+    TimeBudget,
+    Converged,
+    Exploitability,
+    Cancelled,
+}
+
+/// How a call to [`refine_strategy_until`] went
+///
+/// [`refine_strategy_until`]: crate::cfr::strategy_generation::strategy_generator::StrategyGenerator::refine_strategy_until
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StopSummary {
+    pub reason: StopReason,
+    pub iterations: u32,
+    pub elapsed: Duration,
+}
+
+/// A [`StopCondition`] along with what it needs to remember between checks
+pub(crate) enum StopTracker<'h, INFO: VisibleInfo> {
+    Iterations(u32),
+    TimeBudget(Duration),
+    Converged {
+        info: INFO,
+        policy: Policy,
+        epsilon: Probability,
+        over_iterations: u32,
+        last_strategy: Option<StrategyForInfoView<'h, INFO>>,
+        stable_for: u32,
+    },
+    Exploitability {
+        evaluate: Box<EvaluateStrategy<INFO>>,
+        policy: Policy,
+        target: Utility,
+        check_every: u32,
+        // Whether the target was met at the last check
+        met: bool,
+    },
+    Cancelled(CancellationToken),
+    Any(Vec<StopTracker<'h, INFO>>),
+    All(Vec<StopTracker<'h, INFO>>),
+}
+
+impl<'h, INFO: VisibleInfo> StopTracker<'h, INFO> {
+    pub(crate) fn new(condition: StopCondition<INFO>) -> Self {
+        Self::from_kind(condition.kind)
+    }
+
+    fn from_kind(kind: StopKind<INFO>) -> Self {
+        match kind {
+            StopKind::Iterations(n) => Self::Iterations(n),
+            StopKind::TimeBudget(budget) => Self::TimeBudget(budget),
+            StopKind::Converged {
+                info,
+                policy,
+                epsilon,
+                over_iterations,
+            } => Self::Converged {
+                info,
+                policy,
+                epsilon,
+                over_iterations,
+                last_strategy: None,
+                stable_for: 0,
+            },
+            StopKind::Exploitability {
+                evaluate,
+                policy,
+                target,
+                check_every,
+            } => Self::Exploitability {
+                evaluate,
+                policy,
+                target,
+                check_every,
+                met: false,
+            },
+            StopKind::Cancelled(token) => Self::Cancelled(token),
+            StopKind::Any(kinds) => Self::Any(kinds.into_iter().map(Self::from_kind).collect()),
+            StopKind::All(kinds) => Self::All(kinds.into_iter().map(Self::from_kind).collect()),
+        }
+    }
+
+    /// Called before every iteration. Every condition gets checked, even once the answer is
+    /// known, so convergence sees each iteration
+    ///
+    /// `lookup` gives the strategy at an infoset under a policy, or `None` if training never
+    /// reached it. `strategy` gives the probability of each move at any infoset, for evaluating
+    /// the strategy.
+    pub(crate) fn check(
+        &mut self,
+        iterations: u32,
+        elapsed: Duration,
+        lookup: &dyn Fn(&INFO, Policy) -> Option<StrategyForInfoView<'h, INFO>>,
+        strategy: &dyn Fn(&INFO, Policy) -> MoveProbabilities<INFO>,
+    ) -> Option<StopReason> {
+        match self {
+            Self::Iterations(n) => (iterations >= *n).then_some(StopReason::Iterations),
+            Self::TimeBudget(budget) => (elapsed >= *budget).then_some(StopReason::TimeBudget),
+            Self::Converged {
+                info,
+                policy,
+                epsilon,
+                over_iterations,
+                last_strategy,
+                stable_for,
+            } => {
+                // Nothing has converged until training reaches the infoset
+                let strategy = lookup(info, *policy)?;
+
+                if let Some(last_strategy) = last_strategy.as_ref() {
+                    let change = strategy
+                        .iter()
+                        .map(|(m, new)| (new - last_strategy.move_probability(m)).abs())
+                        .fold(0.0, Probability::max);
+
+                    *stable_for = if change < *epsilon {
+                        *stable_for + 1
+                    } else {
+                        0
+                    };
+                }
+                *last_strategy = Some(strategy);
+
+                (*stable_for >= *over_iterations).then_some(StopReason::Converged)
+            }
+            Self::Exploitability {
+                evaluate,
+                policy,
+                target,
+                check_every,
+                met,
+            } => {
+                if iterations.is_multiple_of(*check_every) {
+                    let exploitability = evaluate(&mut |info| strategy(&info, *policy));
+                    *met = exploitability.exploitability() <= *target;
+                }
+
+                met.then_some(StopReason::Exploitability)
+            }
+            Self::Cancelled(token) => token.is_cancelled().then_some(StopReason::Cancelled),
+            Self::Any(trackers) => {
+                let mut first = None;
+                for tracker in trackers {
+                    first = first.or(tracker.check(iterations, elapsed, lookup, strategy));
+                }
+                first
+            }
+            Self::All(trackers) => {
+                let mut last = None;
+                let mut all_met = true;
+                for tracker in trackers {
+                    match tracker.check(iterations, elapsed, lookup, strategy) {
+                        Some(reason) => last = Some(reason),
+                        None => all_met = false,
+                    }
+                }
+                last.filter(|_| all_met)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::stop_condition::{
+        CancellationToken, StopCondition, StopReason,
+    };
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::{board_with_win_for_x, WINNING_MOVE};
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+    use std::time::Duration;
+
+    #[test]
+    fn refining_until_converged() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        let board = board_with_win_for_x();
+
+        let summary = strategy_generator.refine_strategy_until(
+            TicTacToeSampler {
+                board: board.clone(),
+            },
+            StopCondition::converged(board.clone(), Policy::Average, 1e-4, 10)
+                .or(StopCondition::iterations(2000)),
+        );
+        assert_eq!(summary.reason, StopReason::Converged);
+
+        let average = strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Average)
+            .unwrap();
+        assert!(average.move_probability(&WINNING_MOVE) > 0.9);
+    }
+
+    #[test]
+    fn combined_stop_conditions() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+
+        let summary = strategy_generator.refine_strategy_until(
+            sampler.clone(),
+            StopCondition::iterations(5).and(StopCondition::iterations(10)),
+        );
+        assert_eq!(summary.iterations, 10);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let summary = strategy_generator.refine_strategy_until(
+            sampler,
+            StopCondition::cancelled(token).or(StopCondition::time_budget(Duration::MAX)),
+        );
+        assert_eq!(summary.reason, StopReason::Cancelled);
+        assert_eq!(summary.iterations, 0);
+    }
+
+    #[test]
+    fn refining_until_exploitable_enough() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        let target = strategy_generator
+            .exploitability(&sampler, Policy::Average)
+            .exploitability()
+            / 2.0;
+
+        let summary = strategy_generator.refine_strategy_until(
+            sampler.clone(),
+            StopCondition::exploitability(sampler.clone(), Policy::Average, target, 10)
+                .or(StopCondition::iterations(2000)),
+        );
+        assert_eq!(summary.reason, StopReason::Exploitability);
+        assert!(summary.iterations > 0);
+        assert!(summary.iterations.is_multiple_of(10));
+
+        let trained = strategy_generator.exploitability(&sampler, Policy::Average);
+        assert!(trained.exploitability() <= target);
+    }
+}
@@ -1,27 +1,96 @@
 use crate::cfr::game_model::{Probability, VisibleInfo};
+use crate::cfr::strategy_generation::export::StrategyExport;
+use crate::cfr::strategy_generation::fallback::{fall_back, FallbackPolicy};
+use crate::cfr::strategy_generation::frozen_strategy::FrozenStrategy;
 use crate::cfr::strategy_generation::workspace_data::data_for_infoset::DataForInfoSet;
 use crate::cfr::strategy_generation::workspace_data::data_for_known_infosets::DataForKnownInfosets;
-use bumpalo_herd::Member;
+use fastrand::Rng;
 use rustc_hash::FxHashMap;
 use std::collections::hash_map::Iter;
 use std::fmt::{Debug, Formatter};
+use std::io;
+use std::io::Write;
+
+/// Which of the strategies tracked during training to read
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// The reach-weighted average of every iteration's strategy. This is what converges to an
+    /// equilibrium, so it's what should be played
+    #[default]
+    Average,
+    /// The strategy regret matching produced on the latest iteration
+    Current,
+}
+
+/// Where the probabilities of a [`StrategyForInfoView`] came from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StrategySource {
+    /// Training reached the infoset
+    #[default]
+    Trained,
+    /// Borrowed from a similar infoset, see [`NearestInfoset`](crate::cfr::strategy_generation::fallback::NearestInfoset)
+    NearestInfoset,
+    /// Played out in sampled worlds, see [`SampledRollout`](crate::cfr::strategy_generation::fallback::SampledRollout)
+    Rollout,
+    /// Given by a [`Heuristic`](crate::cfr::strategy_generation::fallback::Heuristic)
+    Heuristic,
+    /// Every fallback passed, so every move is equally likely
+    Uniform,
+}
 
 pub struct Strategy<'h, INFO: VisibleInfo> {
     pub(crate) infosets: DataForKnownInfosets<'h, INFO>,
+    pub(crate) fallbacks: Vec<Box<dyn FallbackPolicy<INFO>>>,
 }
 
 impl<'h, INFO: VisibleInfo> Strategy<'h, INFO> {
-    pub fn get_move_probabilities(
+    /// The strategy at `info`, or `None` if training never reached it. This never changes the
+    /// strategy
+    pub fn lookup_move_probabilities(
+        &self,
+        info: &INFO,
+        policy: Policy,
+    ) -> Option<StrategyForInfoView<'h, INFO>> {
+        self.infosets
+            .get(info)
+            .map(|data| StrategyForInfoView::new(data, policy))
+    }
+
+    /// The strategy at `info`, asking the fallbacks the generator was built with if training never
+    /// reached it. [`StrategyForInfoView::source`] tells which it was
+    pub fn move_probabilities_or_fallback(
         &self,
-        info: INFO,
-        member: &Member<'h>,
-    ) -> StrategyForInfoView<INFO> {
-        StrategyForInfoView::new(self.infosets.data_for_infoset(info, member))
+        info: &INFO,
+        policy: Policy,
+        rng: &mut Rng,
+    ) -> StrategyForInfoView<'h, INFO> {
+        self.lookup_move_probabilities(info, policy)
+            .unwrap_or_else(|| {
+                let trained = |other: &INFO| {
+                    self.lookup_move_probabilities(other, policy)
+                        .map(|view| view.iter().map(|(m, p)| (*m, *p)).collect())
+                };
+                fall_back(&self.fallbacks, info, &trained, rng)
+            })
     }
 
-    pub fn pick_move(&self, info: INFO, member: &Member<'h>) -> Option<INFO::Move> {
-        let move_probabilities = self.get_move_probabilities(info, member);
-        move_probabilities.pick_move()
+    /// Picks a move to play at `info`, asking the fallbacks if training never reached it
+    pub fn pick_move(&self, info: &INFO, policy: Policy, rng: &mut Rng) -> Option<INFO::Move> {
+        let move_probabilities = self.move_probabilities_or_fallback(info, policy, rng);
+        move_probabilities.pick_move(rng)
+    }
+
+    /// Copies out the probabilities of every infoset under `policy`, see [`FrozenStrategy`]
+    pub fn freeze(&self, policy: Policy) -> FrozenStrategy<INFO>
+    where
+        INFO: Clone,
+    {
+        FrozenStrategy::from_infosets(&self.infosets, policy)
+    }
+
+    /// Writes out every infoset for inspecting elsewhere, see [`StrategyExport`]
+    pub fn export(&self, export: &StrategyExport, mut w: impl Write) -> io::Result<()> {
+        export.write(&self.infosets, &mut w)
     }
 }
 
@@ -29,34 +98,47 @@ impl<'h, INFO: VisibleInfo> Default for Strategy<'h, INFO> {
     fn default() -> Self {
         Self {
             infosets: Default::default(),
+            fallbacks: Vec::new(),
         }
     }
 }
 
 pub struct StrategyForInfoView<'h, INFO: VisibleInfo> {
-    data_for_info_set: &'h DataForInfoSet<INFO>,
+    // Missing if training never reached the infoset
+    data_for_info_set: Option<&'h DataForInfoSet<INFO>>,
     moves: FxHashMap<INFO::Move, Probability>,
+    terminal: bool,
+    source: StrategySource,
 }
 
 impl<'h, INFO: VisibleInfo> StrategyForInfoView<'h, INFO> {
-    pub(crate) fn new(data_for_info_set: &'h DataForInfoSet<INFO>) -> Self {
+    pub(crate) fn new(data_for_info_set: &'h DataForInfoSet<INFO>, policy: Policy) -> Self {
+        Self {
+            moves: policy_probabilities(data_for_info_set, policy).collect(),
+            terminal: data_for_info_set.is_terminal(),
+            source: StrategySource::Trained,
+            data_for_info_set: Some(data_for_info_set),
+        }
+    }
+
+    /// For an infoset training never reached
+    pub(crate) fn untrained(
+        moves: FxHashMap<INFO::Move, Probability>,
+        terminal: bool,
+        source: StrategySource,
+    ) -> Self {
         Self {
-            moves: data_for_info_set
-                .moves()
-                .iter()
-                .map(|move_with_data| {
-                    (
-                        move_with_data.m,
-                        move_with_data
-                            .d
-                            .load_move_probability(data_for_info_set.moves().len()),
-                    )
-                })
-                .collect(),
-            data_for_info_set,
+            data_for_info_set: None,
+            moves,
+            terminal,
+            source,
         }
     }
 
+    pub fn source(&self) -> StrategySource {
+        self.source
+    }
+
     pub fn iter(&self) -> Iter<'_, INFO::Move, Probability> {
         self.moves.iter()
     }
@@ -76,34 +158,21 @@ impl<'h, INFO: VisibleInfo> StrategyForInfoView<'h, INFO> {
         &self.moves
     }
 
-    pub fn pick_move(&self) -> Option<INFO::Move> {
-        for move_with_data in self.data_for_info_set.moves() {
-            eprintln!(
-                "\tMove {:?} has regret {:?}, probability {:?}",
-                move_with_data.m,
-                move_with_data.d.regret(),
-                move_with_data.d.load_move_probability_unchecked()
-            );
-        }
-
-        if self.data_for_info_set.is_terminal() {
+    pub fn pick_move(&self, rng: &mut Rng) -> Option<INFO::Move> {
+        if self.terminal {
             return None;
         }
 
         let mut moves: Vec<INFO::Move> = self.moves.keys().copied().collect();
 
-        fastrand::shuffle(&mut moves);
+        rng.shuffle(&mut moves);
 
-        let mark = fastrand::f64();
+        let mark = rng.f64();
         let mut cumulative = 0.0;
 
         for m in moves {
             let prob = self.move_probability(&m);
             cumulative += prob;
-            eprintln!(
-                "- Move {:?} has probability {:?} cumm {:?}",
-                m, prob, cumulative
-            );
 
             if mark < cumulative {
                 return Some(m);
@@ -115,12 +184,129 @@ impl<'h, INFO: VisibleInfo> StrategyForInfoView<'h, INFO> {
     }
 }
 
+/// The probability of each move of an infoset under `policy`
+pub(crate) fn policy_probabilities<INFO: VisibleInfo>(
+    data_for_info_set: &DataForInfoSet<INFO>,
+    policy: Policy,
+) -> impl Iterator<Item = (INFO::Move, Probability)> + '_ {
+    let n_moves = data_for_info_set.moves().len();
+    let total_strategy: Probability = data_for_info_set
+        .moves()
+        .iter()
+        .map(|move_with_data| move_with_data.d.strategy_sum())
+        .sum();
+
+    data_for_info_set.moves().iter().map(move |move_with_data| {
+        let probability = match policy {
+            // Until an infoset has been averaged in at least once, the current
+            // strategy is the best guess we have
+            Policy::Average if total_strategy > 0.0 => {
+                move_with_data.d.strategy_sum() / total_strategy
+            }
+            _ => move_with_data.d.load_move_probability(n_moves),
+        };
+
+        (move_with_data.m, probability)
+    })
+}
+
 impl<'h, INFO: VisibleInfo> Debug for StrategyForInfoView<'h, INFO> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str("StrategyForInfo[")?;
-        self.data_for_info_set.fmt(f)?;
+        match self.data_for_info_set {
+            Some(data_for_info_set) => data_for_info_set.fmt(f)?,
+            None => write!(f, "{:?} {:?}", self.source, self.moves)?,
+        }
         f.write_str("]")?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::gadget::Gadget;
+    use crate::cfr::strategy_generation::strategy::{Policy, StrategySource};
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traverser_schedule::Simultaneous;
+    use crate::tic_tac_toe::test::{board_with_win_for_x, WINNING_MOVE};
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use bumpalo_herd::Herd;
+    use fastrand::Rng;
+
+    #[test]
+    fn average_strategy_is_what_was_played() {
+        let herd = Herd::new();
+        let strategy_generator =
+            StrategyGenerator::new(&herd).with_traverser_schedule(Simultaneous);
+        let board = board_with_win_for_x();
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board.clone(),
+            },
+            1,
+        );
+
+        // The first iteration played uniformly, and only the strategy for the next one moved
+        let average = strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Average)
+            .unwrap();
+        assert!(average.iter().all(|(_, p)| (p - 0.2).abs() < 1e-9));
+        let current = strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Current)
+            .unwrap();
+        assert!(current.move_probability(&WINNING_MOVE) > 0.5);
+    }
+
+    #[test]
+    fn looking_up_unseen_infosets() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        let board = board_with_win_for_x();
+        let sampler = TicTacToeSampler {
+            board: board.clone(),
+        };
+
+        // Evaluating plays unseen infosets uniformly, without adding them
+        let untrained = strategy_generator.exploitability(&sampler, Policy::Average);
+        assert!(untrained.nash_conv() > 0.0);
+        strategy_generator.subgame_gadget(&sampler, 1, Gadget::Resolve, Policy::Average);
+        assert!(strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Average)
+            .is_none());
+
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board.clone(),
+            },
+            20,
+        );
+        let trained = strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Average)
+            .unwrap();
+        assert_eq!(trained.move_count(), 5);
+
+        let strategy = strategy_generator.into_strategy();
+        let known = strategy.freeze(Policy::Average).len();
+        assert!(strategy
+            .lookup_move_probabilities(&TicTacToeBoard::default(), Policy::Average)
+            .is_none());
+
+        let uniform = strategy.move_probabilities_or_fallback(
+            &TicTacToeBoard::default(),
+            Policy::Average,
+            &mut Rng::with_seed(0),
+        );
+        assert_eq!(uniform.source(), StrategySource::Uniform);
+        assert!(strategy
+            .pick_move(
+                &TicTacToeBoard::default(),
+                Policy::Average,
+                &mut Rng::with_seed(0)
+            )
+            .is_some());
+
+        // Looking up didn't add anything
+        assert_eq!(strategy.freeze(Policy::Average).len(), known);
+    }
+}
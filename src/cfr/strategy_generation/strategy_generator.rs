@@ -1,46 +1,270 @@
-use crate::cfr::game_model::{GamestateSampler, VisibleInfo};
+use crate::cfr::gadget::{Gadget, GadgetSampler};
+use crate::cfr::game_model::{
+    Encode, EnumerableGamestateSampler, GamestateSampler, PlayerNumber, Probability, VisibleInfo,
+};
+use crate::cfr::strategy_evaluation::{best_response_values, evaluate_strategy, Exploitability};
 use crate::cfr::strategy_generation::cfr_algorithm_impl::accumulate_regret::add_to_regret;
-use crate::cfr::strategy_generation::strategy::{Strategy, StrategyForInfoView};
+use crate::cfr::strategy_generation::cfr_config::{CfrConfig, LinearCfr};
+use crate::cfr::strategy_generation::checkpoint::{read_checkpoint, write_checkpoint};
+use crate::cfr::strategy_generation::depth_limit::DepthLimit;
+use crate::cfr::strategy_generation::export::StrategyExport;
+use crate::cfr::strategy_generation::fallback::{fall_back, uniform, FallbackPolicy};
+use crate::cfr::strategy_generation::observer::{IterationStats, TrainingObserver};
+use crate::cfr::strategy_generation::pruning::RegretPruning;
+use crate::cfr::strategy_generation::stop_condition::{
+    evaluate_over, EvaluateStrategy, StopCondition, StopSummary, StopTracker,
+};
+use crate::cfr::strategy_generation::strategy::{Policy, Strategy, StrategyForInfoView};
+use crate::cfr::strategy_generation::traversal::{ChanceSampling, Traversal, TraversalOptions};
+use crate::cfr::strategy_generation::traverser_schedule::TraverserSchedule;
 use crate::cfr::strategy_generation::update_strategy::update_strategy_from_regret;
+use crate::cfr::strategy_generation::warm_start::WarmStart;
+use crate::cfr::strategy_generation::workspace_data::timestamp::DEFAULT_BATCH_SIZE;
 use crate::cfr::strategy_generation::workspace_data::StrategyGenerationProgress;
+use crate::cfr::strategy_generation::world_sampling::WorldSampling;
 use bumpalo_herd::{Herd, Member};
+use fastrand::Rng;
+use std::io;
+use std::io::{Read, Write};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
 
-pub struct StrategyGenerator<'h, INFO: VisibleInfo> {
+pub struct StrategyGenerator<'h, INFO: VisibleInfo, CONFIG: CfrConfig = LinearCfr> {
     herd: &'h Herd,
     herd_member: Member<'h>,
 
     iterations: AtomicU32,
+    config: CONFIG,
+    traversal_options: TraversalOptions,
+    depth_limit: Option<DepthLimit<INFO>>,
+    observers: Vec<Box<dyn TrainingObserver>>,
+    fallbacks: Vec<Box<dyn FallbackPolicy<INFO>>>,
+    exploitability_every: Option<ExploitabilityEvery<INFO>>,
     strategy_generation_progress: StrategyGenerationProgress<'h, INFO>,
 }
 
+struct ExploitabilityEvery<INFO: VisibleInfo> {
+    evaluate: Box<EvaluateStrategy<INFO>>,
+    policy: Policy,
+    every: u32,
+}
+
 impl<'h, INFO: VisibleInfo> StrategyGenerator<'h, INFO> {
     pub fn new(herd: &'h Herd) -> Self {
+        Self::with_config(herd, LinearCfr::default())
+    }
+}
+
+impl<'h, INFO: VisibleInfo, CONFIG: CfrConfig> StrategyGenerator<'h, INFO, CONFIG> {
+    pub fn with_config(herd: &'h Herd, config: CONFIG) -> Self {
         Self {
             herd,
             herd_member: herd.get(),
             iterations: AtomicU32::new(1),
-            strategy_generation_progress: StrategyGenerationProgress::new(),
+            config,
+            traversal_options: TraversalOptions::default(),
+            depth_limit: None,
+            observers: Vec::new(),
+            fallbacks: Vec::new(),
+            exploitability_every: None,
+            strategy_generation_progress: StrategyGenerationProgress::new(DEFAULT_BATCH_SIZE),
         }
     }
 
+    /// Sets how many worlds get sampled and traversed in parallel on each iteration. Every
+    /// infoset keeps scratch space for each of them, so this trades memory for parallelism.
+    /// Matching the number of cores, as in `rayon::current_num_threads()`, keeps them all busy
+    ///
+    /// Has to be set before any training.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0);
+        assert_eq!(
+            self.strategy_generation_progress.known_infosets(),
+            0,
+            "The batch size can't change once infosets have been created"
+        );
+
+        self.traversal_options
+            .world_sampling
+            .check_batch_size(batch_size);
+        self.strategy_generation_progress
+            .infosets_mut()
+            .set_batch_size(batch_size);
+        self
+    }
+
+    /// Starts every infoset off from `warm_start`'s prior as training first reaches it, instead
+    /// of from uniform play, see [`WarmStart`]
+    pub fn with_warm_start(mut self, warm_start: WarmStart<'h, INFO>) -> Self {
+        self.strategy_generation_progress
+            .infosets_mut()
+            .set_warm_start(warm_start);
+        self
+    }
+
+    /// Sets the seed every random choice made while training is derived from, which is 0 unless
+    /// set otherwise
+    ///
+    /// The same seed and batch size give the same strategy, however many threads there are. Each
+    /// batch item keeps what it adds apart, and they're added up in order between iterations.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.traversal_options.seed = seed;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.traversal_options.seed
+    }
+
+    /// Sets how the worlds for each iteration get drawn, see [`WorldSampling`]
+    pub fn with_world_sampling(mut self, world_sampling: WorldSampling) -> Self {
+        world_sampling.check_batch_size(self.strategy_generation_progress.batch_size());
+        self.traversal_options.world_sampling = world_sampling;
+        self
+    }
+
+    /// Sets which moves get expanded on each traversal, see [`Traversal`]
+    pub fn with_traversal(mut self, traversal: Traversal) -> Self {
+        // Clipping the importance-weighted regret of a single trajectory biases it
+        assert!(
+            !matches!(traversal, Traversal::OutcomeSampling { .. })
+                || self.config.clip_instant_regret(-1.0) < 0.0,
+            "outcome sampling needs a config that doesn't clip instantaneous regret"
+        );
+        self.traversal_options.traversal = traversal;
+        self
+    }
+
+    /// Sets how chance outcomes get handled, see [`ChanceSampling`]
+    pub fn with_chance_sampling(mut self, chance_sampling: ChanceSampling) -> Self {
+        self.traversal_options.chance_sampling = chance_sampling;
+        self
+    }
+
+    /// Turns on regret-based pruning, see [`RegretPruning`]
+    pub fn with_pruning(mut self, pruning: RegretPruning) -> Self {
+        assert!(
+            pruning.recheck_every > 0,
+            "pruned moves need to be rechecked at some point"
+        );
+        // Moves only get pruned once their regret is below the threshold
+        assert!(
+            pruning.threshold > 0.0
+                || self.config.clip_instant_regret(-1.0) < 0.0
+                    && self.config.discount_cumulative_regret(-1.0, 1) < 0.0,
+            "pruning needs a config that lets cumulative regret go negative"
+        );
+        self.traversal_options.pruning = Some(pruning);
+        self
+    }
+
+    /// Sets whose regrets get updated on each traversal, see [`TraverserSchedule`]
+    pub fn with_traverser_schedule(mut self, schedule: impl TraverserSchedule + 'static) -> Self {
+        self.traversal_options.schedule = Box::new(schedule);
+        self
+    }
+
+    /// Tells `observer` about every iteration from now on, see [`TrainingObserver`]
+    pub fn with_observer(mut self, observer: impl TrainingObserver + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Evaluates the strategy over the worlds of `sampler` after every `every`th iteration, for
+    /// [`IterationStats::exploitability`], such as to plot how training converges
+    ///
+    /// Evaluating walks the entire game tree, which can easily take longer than the iteration
+    /// itself. It isn't counted in the iteration's times.
+    pub fn with_exploitability_every<SAMPLER>(
+        mut self,
+        sampler: SAMPLER,
+        policy: Policy,
+        every: u32,
+    ) -> Self
+    where
+        SAMPLER: EnumerableGamestateSampler<Info = INFO> + Sync + 'static,
+    {
+        assert!(
+            every > 0,
+            "exploitability has to be evaluated at some point"
+        );
+        self.exploitability_every = Some(ExploitabilityEvery {
+            evaluate: evaluate_over(sampler),
+            policy,
+            every,
+        });
+        self
+    }
+
+    /// Asks `fallback` what to play at infosets training never reached, after any fallbacks added
+    /// before it, see [`FallbackPolicy`]
+    pub fn with_fallback(mut self, fallback: impl FallbackPolicy<INFO> + 'static) -> Self {
+        self.fallbacks.push(Box::new(fallback));
+        self
+    }
+
+    /// Stops traversals early, estimating the utility from there on, see [`DepthLimit`]
+    pub fn with_depth_limit(mut self, depth_limit: DepthLimit<INFO>) -> Self {
+        self.depth_limit = Some(depth_limit);
+        self
+    }
+
+    pub fn config(&self) -> &CONFIG {
+        &self.config
+    }
+
     fn advance_strategy_once<GENERATOR: GamestateSampler<Info = INFO>>(
         &self,
         starting_gamestate_sampler: GENERATOR,
     ) {
         let iteration = self.iterations.fetch_add(1, Ordering::Relaxed);
-        eprintln!("Starting Iteration {}", iteration);
+        let known_infosets = self.strategy_generation_progress.known_infosets();
 
+        let traversal_start = Instant::now();
         add_to_regret(
             starting_gamestate_sampler,
             &self.strategy_generation_progress,
             self.herd,
             iteration,
+            &self.config,
+            &self.traversal_options,
+            self.depth_limit.as_ref(),
         );
-        eprintln!("Switching to strategy update {}", iteration);
+        let traversal_time = traversal_start.elapsed();
 
-        update_strategy_from_regret(&self.strategy_generation_progress);
-        eprintln!("Ending Iteration {}", iteration);
+        let update_start = Instant::now();
+        let update = update_strategy_from_regret(
+            &self.strategy_generation_progress,
+            &self.config,
+            self.traversal_options.pruning.as_ref(),
+            iteration,
+        );
+        let update_time = update_start.elapsed();
+
+        let stats = IterationStats {
+            iteration,
+            traversal_time,
+            update_time,
+            infosets_created: self
+                .strategy_generation_progress
+                .known_infosets()
+                .saturating_sub(known_infosets),
+            infosets_updated: update.infosets_updated,
+            total_positive_regret: update.total_positive_regret,
+            average_strategy_change: update.average_strategy_change,
+            peak_workstack_depth: self
+                .strategy_generation_progress
+                .take_peak_workstack_depth(),
+            moves_pruned: self.strategy_generation_progress.take_moves_pruned(),
+            exploitability: self
+                .exploitability_every
+                .as_ref()
+                .filter(|e| iteration.is_multiple_of(e.every))
+                .map(|e| (e.evaluate)(&mut |info| self.trained_or_uniform(&info, e.policy))),
+        };
+        for observer in &self.observers {
+            observer.on_iteration(&stats);
+        }
     }
 
     pub fn refine_strategy<GENERATOR: GamestateSampler<Info = INFO>>(
@@ -53,17 +277,292 @@ impl<'h, INFO: VisibleInfo> StrategyGenerator<'h, INFO> {
         }
     }
 
-    pub fn strategy_for_info(&self, state: INFO) -> StrategyForInfoView<'h, INFO> {
+    /// Refines the strategy until `condition` is met, returning why it stopped
+    pub fn refine_strategy_until<GENERATOR: GamestateSampler<Info = INFO>>(
+        &self,
+        starting_gamestate_sampler: GENERATOR,
+        condition: StopCondition<INFO>,
+    ) -> StopSummary {
+        let start = Instant::now();
+        let mut tracker = StopTracker::new(condition);
+
+        let mut iterations = 0;
+        loop {
+            let elapsed = start.elapsed();
+            if let Some(reason) = tracker.check(
+                iterations,
+                elapsed,
+                &|info, policy| self.lookup_strategy_for_info(info, policy),
+                &|info, policy| self.trained_or_uniform(info, policy),
+            ) {
+                return StopSummary {
+                    reason,
+                    iterations,
+                    elapsed,
+                };
+            }
+
+            self.advance_strategy_once(starting_gamestate_sampler.clone());
+            iterations += 1;
+        }
+    }
+
+    /// Refines the strategy for the subgame that the worlds of `starting_gamestate_sampler` start
+    /// from, such as after a move has been played for real
+    ///
+    /// Training carries on from everything learnt so far, but afterwards any infoset that wasn't
+    /// reached while re-solving gets discarded, so branches the game didn't take stop counting.
+    pub fn resolve_subgame<GENERATOR: GamestateSampler<Info = INFO>>(
+        &self,
+        starting_gamestate_sampler: GENERATOR,
+        n: u32,
+    ) {
+        let first_iteration = self.iterations.load(Ordering::Relaxed);
+        self.refine_strategy(starting_gamestate_sampler, n);
+
+        self.strategy_generation_progress
+            .discard_infosets_not_visited_since(first_iteration);
+    }
+
+    /// Writes everything training has learnt so far to `w`, so a fresh generator can carry on
+    /// from it with [`Self::restore_checkpoint`]
+    ///
+    /// Settings such as the config, traversal and seed aren't part of the checkpoint. Training
+    /// only carries on exactly where it left off if the restored generator is built with the same
+    /// ones. This writes lots of small pieces, so `w` is best buffered.
+    pub fn save_checkpoint(&self, mut w: impl Write) -> io::Result<()>
+    where
+        INFO: Encode,
+        INFO::Move: Encode,
+    {
+        write_checkpoint(
+            &self.strategy_generation_progress,
+            self.iterations.load(Ordering::Relaxed),
+            &mut w,
+        )
+    }
+
+    /// Restores a checkpoint from [`Self::save_checkpoint`] into this generator, which can't have
+    /// been trained yet
+    ///
+    /// If this fails, the generator is left partly restored and shouldn't be trained further.
+    pub fn restore_checkpoint(&self, mut r: impl Read) -> io::Result<()>
+    where
+        INFO: Encode,
+        INFO::Move: Encode,
+    {
+        assert_eq!(
+            self.strategy_generation_progress.known_infosets(),
+            0,
+            "Checkpoints can only be restored into a fresh generator"
+        );
+
+        let next_iteration = read_checkpoint(
+            &self.strategy_generation_progress,
+            &self.herd_member,
+            &mut r,
+        )?;
+        self.iterations.store(next_iteration, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Writes out every infoset trained so far for inspecting elsewhere, see [`StrategyExport`]
+    pub fn export(&self, export: &StrategyExport, mut w: impl Write) -> io::Result<()> {
+        export.write(self.strategy_generation_progress.infosets(), &mut w)
+    }
+
+    /// Infosets that weren't trained get added with a uniform strategy
+    #[deprecated(
+        note = "adds infosets training never reached, use `lookup_strategy_for_info` or \
+                `strategy_for_info_or_fallback`"
+    )]
+    pub fn strategy_for_info(&self, state: INFO, policy: Policy) -> StrategyForInfoView<'h, INFO> {
         let data_for_info = self
             .strategy_generation_progress
             .get_data_for_infoset(state, &self.herd_member);
 
-        StrategyForInfoView::new(data_for_info)
+        StrategyForInfoView::new(data_for_info, policy)
+    }
+
+    /// The strategy at `info`, or `None` if training never reached it. This never changes the
+    /// strategy
+    pub fn lookup_strategy_for_info(
+        &self,
+        info: &INFO,
+        policy: Policy,
+    ) -> Option<StrategyForInfoView<'h, INFO>> {
+        self.strategy_generation_progress
+            .infosets()
+            .get(info)
+            .map(|data| StrategyForInfoView::new(data, policy))
+    }
+
+    /// The strategy at `info`, asking the fallbacks if training never reached it.
+    /// [`StrategyForInfoView::source`] tells which it was
+    pub fn strategy_for_info_or_fallback(
+        &self,
+        info: &INFO,
+        policy: Policy,
+        rng: &mut Rng,
+    ) -> StrategyForInfoView<'h, INFO> {
+        self.lookup_strategy_for_info(info, policy)
+            .unwrap_or_else(|| {
+                let trained = |other: &INFO| {
+                    self.lookup_strategy_for_info(other, policy)
+                        .map(|view| view.iter().map(|(m, p)| (*m, *p)).collect())
+                };
+                fall_back(&self.fallbacks, info, &trained, rng)
+            })
+    }
+
+    /// Exactly evaluates the strategy for every world of `sampler`, see [`evaluate_strategy`]
+    ///
+    /// Infosets that training never reached are played uniformly at random, without adding them.
+    pub fn exploitability<SAMPLER: EnumerableGamestateSampler<Info = INFO>>(
+        &self,
+        sampler: &SAMPLER,
+        policy: Policy,
+    ) -> Exploitability {
+        evaluate_strategy(sampler, |info| self.trained_or_uniform(&info, policy))
+    }
+
+    /// Builds a gadget game for safely re-solving the subgame that starts from the worlds of
+    /// `sampler`, see [`Gadget`]
+    ///
+    /// The opponent's alternatives are what they get from best responding to the strategy so far.
+    /// The probabilities of the worlds should include how likely everyone but `opponent` were to
+    /// reach them, so the alternatives are weighted the way the opponent would weigh them.
+    pub fn subgame_gadget<SAMPLER: EnumerableGamestateSampler<Info = INFO>>(
+        &self,
+        sampler: &SAMPLER,
+        opponent: PlayerNumber,
+        gadget: Gadget,
+        policy: Policy,
+    ) -> GadgetSampler<INFO> {
+        let worlds = sampler.enumerate();
+        let alternatives = best_response_values(&worlds, opponent, |info| {
+            self.trained_or_uniform(&info, policy)
+        });
+
+        GadgetSampler::new(gadget, opponent, worlds, alternatives)
+    }
+
+    /// Evaluating shouldn't change the strategy, so infosets training never reached are played
+    /// uniformly rather than added
+    fn trained_or_uniform(&self, info: &INFO, policy: Policy) -> Vec<(INFO::Move, Probability)> {
+        self.lookup_strategy_for_info(info, policy)
+            .unwrap_or_else(|| uniform(info))
+            .iter()
+            .map(|(m, p)| (*m, *p))
+            .collect()
     }
 
     pub fn into_strategy(self) -> Strategy<'h, INFO> {
         Strategy {
             infosets: self.strategy_generation_progress.into_infoset_data(),
+            fallbacks: self.fallbacks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::cfr_config::DiscountedCfr;
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::tic_tac_toe::test::{board_with_win_for_x, WINNING_MOVE};
+    use crate::tic_tac_toe::{Player, TicTacToeBoard, TicTacToeSampler, TicTacToeSquare};
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn resolving_forgets_branches_the_game_did_not_take() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+
+        let mut blunder = board_with_win_for_x();
+        blunder.squares[4] = TicTacToeSquare::Empty;
+        blunder.turn = Player::O;
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: blunder.clone(),
+            },
+            100,
+        );
+
+        let board = board_with_win_for_x();
+        strategy_generator.resolve_subgame(
+            TicTacToeSampler {
+                board: board.clone(),
+            },
+            200,
+        );
+
+        let average = strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Average)
+            .unwrap();
+        assert!(average.move_probability(&WINNING_MOVE) > 0.9);
+
+        // The blunder is in the past, so there's no need to keep training it
+        assert!(strategy_generator
+            .lookup_strategy_for_info(&blunder, Policy::Average)
+            .is_none());
+    }
+
+    #[test]
+    fn every_batch_item_traverses_a_world() {
+        for batch_size in [1, 16] {
+            let herd = Herd::new();
+            let strategy_generator = StrategyGenerator::new(&herd).with_batch_size(batch_size);
+            let board = board_with_win_for_x();
+            strategy_generator.refine_strategy(
+                TicTacToeSampler {
+                    board: board.clone(),
+                },
+                1,
+            );
+
+            let visits = strategy_generator
+                .strategy_generation_progress
+                .get_data_for_infoset(board, &strategy_generator.herd_member)
+                .visits();
+            assert_eq!(visits, batch_size as u64);
         }
     }
+
+    #[test]
+    fn same_seed_gives_the_same_strategy() {
+        let train = |seed, threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+
+            pool.install(|| {
+                let herd = Herd::new();
+                let strategy_generator =
+                    StrategyGenerator::with_config(&herd, DiscountedCfr::default())
+                        .with_batch_size(16)
+                        .with_seed(seed);
+                strategy_generator.refine_strategy(
+                    TicTacToeSampler {
+                        board: TicTacToeBoard::default(),
+                    },
+                    20,
+                );
+
+                let strategy = strategy_generator
+                    .lookup_strategy_for_info(&TicTacToeBoard::default(), Policy::Average)
+                    .unwrap();
+                let mut probabilities: Vec<_> =
+                    strategy.iter().map(|(m, p)| (m.square, *p)).collect();
+                probabilities.sort_by_key(|(square, _)| *square);
+                probabilities
+            })
+        };
+
+        let first = train(7, 4);
+        assert_eq!(first, train(7, 4));
+        assert_eq!(first, train(7, 1));
+        assert_ne!(first, train(8, 4));
+    }
 }
@@ -0,0 +1,470 @@
+use crate::cfr::game_model::{PlayerNumber, Probability};
+use crate::cfr::strategy_generation::pruning::RegretPruning;
+use crate::cfr::strategy_generation::traverser_schedule::{
+    Alternating, TraverserSchedule, Traversers,
+};
+use crate::cfr::strategy_generation::world_sampling::WorldSampling;
+
+/// Which moves get expanded when traversing the game tree
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Traversal {
+    /// Vanilla CFR. Every move of every player is expanded, so each traversal is exact but has to
+    /// visit the whole tree
+    Full,
+    /// External-sampling Monte Carlo CFR. Every move of the traverser is expanded, while everyone
+    /// else plays a single move sampled from their current strategy. Traversals are far cheaper,
+    /// at the cost of noisier regret
+    #[default]
+    ExternalSampling,
+    /// Outcome-sampling Monte Carlo CFR. Only a single trajectory is followed. The traverser
+    /// samples from their current strategy mixed with a uniform one, taking a uniformly random
+    /// move with probability `exploration`, and regrets are corrected by importance sampling.
+    /// Very cheap per traversal, but needs many more of them to converge.
+    ///
+    /// The importance-weighted regret is only unbiased before clipping, so this needs a config
+    /// that doesn't clip instantaneous regret, like
+    /// [`DiscountedCfr`](crate::cfr::strategy_generation::cfr_config::DiscountedCfr)
+    OutcomeSampling { exploration: Probability },
+}
+
+/// How chance outcomes get handled when the move leading to them is expanded
+///
+/// Chance after a sampled move is always sampled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChanceSampling {
+    /// Every outcome is visited and weighted by its probability
+    ///
+    /// Each outcome is traversed on a path of its own, so this works for chance that some
+    /// players can't see the outcome of too, like a deal. Outcomes that someone can't tell apart
+    /// each add their own regret to the infoset they share.
+    Enumerate,
+    /// A single outcome is visited, sampled by its probability
+    #[default]
+    Sample,
+}
+
+/// Everything set on the generator about how the game tree gets traversed
+#[derive(Debug)]
+pub(crate) struct TraversalOptions {
+    pub(crate) traversal: Traversal,
+    pub(crate) chance_sampling: ChanceSampling,
+    pub(crate) pruning: Option<RegretPruning>,
+    pub(crate) schedule: Box<dyn TraverserSchedule>,
+    pub(crate) world_sampling: WorldSampling,
+    // Every random choice made while training is derived from this
+    pub(crate) seed: u64,
+}
+
+impl Default for TraversalOptions {
+    fn default() -> Self {
+        Self {
+            traversal: Traversal::default(),
+            chance_sampling: ChanceSampling::default(),
+            pruning: None,
+            schedule: Box::new(Alternating),
+            world_sampling: WorldSampling::default(),
+            seed: 0,
+        }
+    }
+}
+
+/// How the moves at a single infoset get handled by a traversal
+pub(crate) enum MoveSelection {
+    /// Every move gets visited
+    Expand,
+    /// A single move gets visited, sampled from the current strategy
+    Sample,
+    /// A single move gets visited, sampled with the given amount of exploration
+    Explore(Probability),
+}
+
+impl Traversal {
+    pub(crate) fn move_selection(
+        &self,
+        turn: PlayerNumber,
+        traversers: Traversers,
+    ) -> MoveSelection {
+        match self {
+            Traversal::Full => MoveSelection::Expand,
+            Traversal::ExternalSampling if traversers.contains(turn) => MoveSelection::Expand,
+            Traversal::OutcomeSampling { exploration } if traversers.contains(turn) => {
+                MoveSelection::Explore(*exploration)
+            }
+            Traversal::ExternalSampling | Traversal::OutcomeSampling { .. } => {
+                MoveSelection::Sample
+            }
+        }
+    }
+
+    /// Whether infosets of `turn` contribute to the average strategy on this traversal
+    ///
+    /// With external sampling, only the players whose moves are sampled reach their infosets in
+    /// proportion to how likely they are to play there, so only they give an unbiased
+    /// contribution. When everyone is traversed at once there are no such players, but the
+    /// traverser's own reach is tracked exactly and the rest is corrected for by how likely it
+    /// was sampled. Outcome sampling corrects the traverser's reach in the same way.
+    pub(crate) fn averages_at(
+        &self,
+        turn: PlayerNumber,
+        traversers: Traversers,
+        players_playing: PlayerNumber,
+    ) -> bool {
+        match self {
+            Traversal::Full => true,
+            Traversal::ExternalSampling => {
+                !traversers.contains(turn) || traversers.contains_all(players_playing)
+            }
+            Traversal::OutcomeSampling { .. } => traversers.contains(turn),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::game_model::{
+        EnumerableGamestateSampler, GamestateSampler, OracleGamestate, PlayerNumber, Probability,
+        UtilityForAllPlayers, VisibleInfo,
+    };
+    use crate::cfr::strategy_generation::cfr_config::DiscountedCfr;
+    use crate::cfr::strategy_generation::observer::test::CollectStats;
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::{ChanceSampling, Traversal};
+    use crate::cfr::strategy_generation::traverser_schedule::Simultaneous;
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use bumpalo_herd::Herd;
+    use std::sync::Arc;
+
+    /// A biased coin is flipped where everyone can see it, then player 0 calls it. Calling tails
+    /// correctly pays more, to make sure outcomes get weighted by their probability
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum CoinGame {
+        Flip,
+        Call { heads: bool },
+        Called { heads: bool, called_heads: bool },
+    }
+
+    impl OracleGamestate<CoinGame> for CoinGame {
+        fn info_for_turn_player(&self) -> CoinGame {
+            *self
+        }
+
+        fn info_for_player(&self, _player: PlayerNumber) -> CoinGame {
+            *self
+        }
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn advance(&self, called_heads: &bool) -> Self {
+            match self {
+                CoinGame::Call { heads } => CoinGame::Called {
+                    heads: *heads,
+                    called_heads: *called_heads,
+                },
+                _ => unreachable!(),
+            }
+        }
+
+        fn chance_outcomes(&self) -> Option<Vec<(Self, Probability)>> {
+            match self {
+                CoinGame::Flip => Some(vec![
+                    (CoinGame::Call { heads: true }, 0.75),
+                    (CoinGame::Call { heads: false }, 0.25),
+                ]),
+                _ => None,
+            }
+        }
+    }
+
+    impl VisibleInfo for CoinGame {
+        type Move = bool;
+        type Gamestate = CoinGame;
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn run_for_moves(&self, mut f: impl FnMut(bool)) -> Option<UtilityForAllPlayers> {
+            match self {
+                CoinGame::Called {
+                    heads,
+                    called_heads,
+                } => {
+                    let payout = match (heads, called_heads) {
+                        (true, true) => 1.0,
+                        (false, false) => 3.0,
+                        _ => 0.0,
+                    };
+                    Some(UtilityForAllPlayers {
+                        util: [payout, 0.0, 0.0, 0.0],
+                    })
+                }
+                _ => {
+                    f(true);
+                    f(false);
+                    None
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct CoinGameSampler;
+
+    impl GamestateSampler for CoinGameSampler {
+        type Info = CoinGame;
+
+        fn sample(&mut self, _rng: &mut fastrand::Rng) -> (CoinGame, Probability) {
+            (CoinGame::Flip, 1.0)
+        }
+    }
+
+    impl EnumerableGamestateSampler for CoinGameSampler {
+        fn enumerate(&self) -> Vec<(CoinGame, Probability)> {
+            vec![(CoinGame::Flip, 1.0)]
+        }
+    }
+
+    /// The same biased coin, but player 0 has to call it without seeing it, winning 1 if they're
+    /// right and losing 1 if they're wrong
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum HiddenCoinGame {
+        Flip,
+        Call { heads: bool },
+        Called { heads: bool, called_heads: bool },
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum HiddenCoinInfo {
+        Call,
+        Called { won: bool },
+    }
+
+    impl OracleGamestate<HiddenCoinInfo> for HiddenCoinGame {
+        fn info_for_turn_player(&self) -> HiddenCoinInfo {
+            self.info_for_player(0)
+        }
+
+        fn info_for_player(&self, _player: PlayerNumber) -> HiddenCoinInfo {
+            match self {
+                HiddenCoinGame::Called {
+                    heads,
+                    called_heads,
+                } => HiddenCoinInfo::Called {
+                    won: heads == called_heads,
+                },
+                _ => HiddenCoinInfo::Call,
+            }
+        }
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn advance(&self, called_heads: &bool) -> Self {
+            match self {
+                HiddenCoinGame::Call { heads } => HiddenCoinGame::Called {
+                    heads: *heads,
+                    called_heads: *called_heads,
+                },
+                _ => unreachable!(),
+            }
+        }
+
+        fn chance_outcomes(&self) -> Option<Vec<(Self, Probability)>> {
+            match self {
+                HiddenCoinGame::Flip => Some(vec![
+                    (HiddenCoinGame::Call { heads: true }, 0.75),
+                    (HiddenCoinGame::Call { heads: false }, 0.25),
+                ]),
+                _ => None,
+            }
+        }
+    }
+
+    impl VisibleInfo for HiddenCoinInfo {
+        type Move = bool;
+        type Gamestate = HiddenCoinGame;
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn run_for_moves(&self, mut f: impl FnMut(bool)) -> Option<UtilityForAllPlayers> {
+            match self {
+                HiddenCoinInfo::Called { won } => Some(UtilityForAllPlayers {
+                    util: [if *won { 1.0 } else { -1.0 }, 0.0, 0.0, 0.0],
+                }),
+                HiddenCoinInfo::Call => {
+                    f(true);
+                    f(false);
+                    None
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct HiddenCoinGameSampler;
+
+    impl GamestateSampler for HiddenCoinGameSampler {
+        type Info = HiddenCoinInfo;
+
+        fn sample(&mut self, _rng: &mut fastrand::Rng) -> (HiddenCoinGame, Probability) {
+            (HiddenCoinGame::Flip, 1.0)
+        }
+    }
+
+    #[test]
+    fn enumerated_hidden_chance_adds_regret_for_every_outcome() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_traversal(Traversal::Full)
+            .with_chance_sampling(ChanceSampling::Enumerate)
+            .with_traverser_schedule(Simultaneous)
+            .with_batch_size(1);
+        strategy_generator.refine_strategy(HiddenCoinGameSampler, 1);
+
+        // Against the uniform strategy, which is worth 0, heads gains 1 three times in four and
+        // loses 1 otherwise
+        let strategy = strategy_generator.into_strategy();
+        let call = strategy.infosets.get(&HiddenCoinInfo::Call).unwrap();
+        assert_eq!(call.visits(), 2);
+        let [heads, tails] = call.moves() else {
+            unreachable!()
+        };
+        assert!(heads.m);
+        assert!(
+            (heads.d.regret() - 0.5).abs() < 1e-9,
+            "{}",
+            heads.d.regret()
+        );
+        assert_eq!(tails.d.regret(), 0.0);
+    }
+
+    #[test]
+    fn enumerated_chance_is_weighted_by_probability() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_traversal(Traversal::Full)
+            .with_chance_sampling(ChanceSampling::Enumerate);
+
+        let untrained = strategy_generator.exploitability(&CoinGameSampler, Policy::Average);
+        assert!((untrained.strategy_value.get(0) - 0.75).abs() < 1e-9);
+        assert!((untrained.best_response_value.get(0) - 1.5).abs() < 1e-9);
+
+        strategy_generator.refine_strategy(CoinGameSampler, 50);
+        let trained = strategy_generator.exploitability(&CoinGameSampler, Policy::Average);
+        assert!(trained.gain(0) < 0.1);
+    }
+
+    #[test]
+    fn external_sampling_keeps_up_with_full_traversal() {
+        let herd = Herd::new();
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        let untrained = StrategyGenerator::new(&herd)
+            .exploitability(&sampler, Policy::Average)
+            .nash_conv();
+
+        // Updating infosets is most of the work, so both get the same budget of updates
+        let trained = |traversal| {
+            let observer = Arc::new(CollectStats::default());
+            let strategy_generator = StrategyGenerator::new(&herd)
+                .with_traversal(traversal)
+                .with_observer(observer.clone());
+            let updates =
+                || -> usize { observer.0.lock().iter().map(|s| s.infosets_updated).sum() };
+            while updates() < 10_000 {
+                strategy_generator.refine_strategy(sampler.clone(), 1);
+            }
+            strategy_generator
+                .exploitability(&sampler, Policy::Average)
+                .nash_conv()
+        };
+        let full = trained(Traversal::Full);
+        let external_sampling = trained(Traversal::ExternalSampling);
+
+        // Sampling the opponent's moves makes the regret noisier, but each iteration is that much
+        // cheaper
+        assert!(external_sampling < full * 2.0);
+        assert!(external_sampling < untrained / 100.0);
+    }
+
+    #[test]
+    fn outcome_sampling_follows_a_single_trajectory() {
+        let infosets_after_one_traversal = |traversal| {
+            let herd = Herd::new();
+            let strategy_generator =
+                StrategyGenerator::with_config(&herd, DiscountedCfr::default())
+                    .with_batch_size(1)
+                    .with_traversal(traversal);
+            strategy_generator.refine_strategy(
+                TicTacToeSampler {
+                    board: TicTacToeBoard::default(),
+                },
+                1,
+            );
+            let strategy = strategy_generator.into_strategy();
+            strategy.infosets.len()
+        };
+
+        // At most a move into each square, and the end of the game
+        assert!(
+            infosets_after_one_traversal(Traversal::OutcomeSampling { exploration: 0.6 }) <= 10
+        );
+        assert!(infosets_after_one_traversal(Traversal::ExternalSampling) > 10);
+    }
+
+    #[test]
+    fn outcome_sampling_reduces_exploitability() {
+        let herd = Herd::new();
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        let strategy_generator = StrategyGenerator::with_config(&herd, DiscountedCfr::default())
+            .with_traversal(Traversal::OutcomeSampling { exploration: 0.6 });
+        let untrained = strategy_generator
+            .exploitability(&sampler, Policy::Average)
+            .nash_conv();
+
+        // Each traversal only follows a single trajectory, so it takes more of them
+        strategy_generator.refine_strategy(sampler.clone(), 800);
+        let trained = strategy_generator
+            .exploitability(&sampler, Policy::Average)
+            .nash_conv();
+
+        assert!(trained < untrained / 5.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "outcome sampling needs a config that doesn't clip instantaneous regret"
+    )]
+    fn outcome_sampling_needs_unclipped_regret() {
+        let herd = Herd::new();
+        // Linear CFR clips instantaneous regret at zero
+        StrategyGenerator::<TicTacToeBoard>::new(&herd)
+            .with_traversal(Traversal::OutcomeSampling { exploration: 0.6 });
+    }
+}
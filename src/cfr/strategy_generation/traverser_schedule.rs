@@ -0,0 +1,273 @@
+use crate::cfr::game_model::{PlayerNumber, MAX_PLAYERS};
+use std::fmt::Debug;
+
+/// The players whose regrets get updated on a single traversal
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Traversers(u8);
+
+const _: () = assert!(MAX_PLAYERS <= u8::BITS as usize);
+
+impl Traversers {
+    pub const fn single(player: PlayerNumber) -> Self {
+        Self(1 << player)
+    }
+
+    pub const fn all(players_playing: PlayerNumber) -> Self {
+        Self(((1u16 << players_playing) - 1) as u8)
+    }
+
+    pub const fn with(self, player: PlayerNumber) -> Self {
+        Self(self.0 | 1 << player)
+    }
+
+    pub const fn contains(&self, player: PlayerNumber) -> bool {
+        self.0 & 1 << player != 0
+    }
+
+    pub const fn contains_all(&self, players_playing: PlayerNumber) -> bool {
+        let all = Self::all(players_playing).0;
+        self.0 & all == all
+    }
+}
+
+impl FromIterator<PlayerNumber> for Traversers {
+    fn from_iter<T: IntoIterator<Item = PlayerNumber>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::default(), Self::with)
+    }
+}
+
+/// Decides whose regrets get updated on each traversal
+pub trait TraverserSchedule: Debug + Send + Sync {
+    /// The players to traverse for `batch_item` on `iteration`
+    fn traversers(
+        &self,
+        iteration: u32,
+        batch_item: usize,
+        players_playing: PlayerNumber,
+    ) -> Traversers;
+}
+
+/// One player per traversal, taking turns
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Alternating;
+
+impl TraverserSchedule for Alternating {
+    fn traversers(
+        &self,
+        iteration: u32,
+        batch_item: usize,
+        players_playing: PlayerNumber,
+    ) -> Traversers {
+        Traversers::single((iteration as PlayerNumber + batch_item) % players_playing)
+    }
+}
+
+/// Every player on every traversal
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Simultaneous;
+
+impl TraverserSchedule for Simultaneous {
+    fn traversers(
+        &self,
+        _iteration: u32,
+        _batch_item: usize,
+        players_playing: PlayerNumber,
+    ) -> Traversers {
+        Traversers::all(players_playing)
+    }
+}
+
+/// One player per traversal, picked in proportion to `weights[player]`
+///
+/// Useful to spend more of the effort on the players whose strategy is hardest to learn. Players
+/// are picked by a deterministic low-discrepancy sequence, so even over a few iterations each
+/// gets close to their share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weighted {
+    weights: Vec<f64>,
+}
+
+impl Weighted {
+    /// `weights` needs one for each player, and at least one of them above zero
+    pub fn new(weights: Vec<f64>) -> Self {
+        assert!(
+            weights
+                .iter()
+                .all(|weight| weight.is_finite() && *weight >= 0.0),
+            "weights can't be negative"
+        );
+        assert!(
+            weights.iter().any(|weight| *weight > 0.0),
+            "some player needs to be traversed"
+        );
+
+        Self { weights }
+    }
+}
+
+impl TraverserSchedule for Weighted {
+    fn traversers(
+        &self,
+        iteration: u32,
+        batch_item: usize,
+        players_playing: PlayerNumber,
+    ) -> Traversers {
+        assert!(
+            self.weights.len() >= players_playing,
+            "every player needs a weight"
+        );
+        let weights = &self.weights[..players_playing];
+        let total: f64 = weights.iter().sum();
+        assert!(total > 0.0, "some player needs to be traversed");
+
+        // Fractional parts of multiples of the golden ratio spread out evenly over [0, 1). Batch
+        // items get offset by a different irrational, so they don't all pick the same player
+        const GOLDEN_RATIO_FRACTION: f64 = 0.618_033_988_749_894_9;
+        const SQRT_2_FRACTION: f64 = 0.414_213_562_373_095_1;
+        let mark = (iteration as f64 * GOLDEN_RATIO_FRACTION + batch_item as f64 * SQRT_2_FRACTION)
+            .fract()
+            * total;
+
+        let mut cumulative = 0.0;
+        for (player, weight) in weights.iter().enumerate() {
+            cumulative += weight;
+            if mark < cumulative {
+                return Traversers::single(player);
+            }
+        }
+
+        // Rounding can leave the cumulative weight a hair short of the total
+        Traversers::single(players_playing - 1)
+    }
+}
+
+/// Teams of players that share a utility get traversed together, taking turns
+///
+/// Players on a team are effectively one agent, like declarer and dummy in bridge, so it's
+/// wasted effort to traverse them separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Team {
+    teams: Vec<Traversers>,
+    // Everyone on any of the teams
+    covered: Traversers,
+}
+
+impl Team {
+    /// No player can be on more than one team, and every player in the game has to be on one
+    pub fn new(teams: Vec<Traversers>) -> Self {
+        let mut covered = Traversers::default();
+        for team in &teams {
+            assert!(*team != Traversers::default(), "teams can't be empty");
+            assert!(team.0 & covered.0 == 0, "players can only be on one team");
+            covered.0 |= team.0;
+        }
+
+        Self { teams, covered }
+    }
+}
+
+impl TraverserSchedule for Team {
+    fn traversers(
+        &self,
+        iteration: u32,
+        batch_item: usize,
+        players_playing: PlayerNumber,
+    ) -> Traversers {
+        assert!(
+            self.covered.contains_all(players_playing),
+            "every player needs to be on a team"
+        );
+
+        self.teams[(iteration as usize + batch_item) % self.teams.len()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::observer::test::CollectStats;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::traversal::Traversal;
+    use crate::cfr::strategy_generation::traverser_schedule::{
+        Alternating, Simultaneous, Team, TraverserSchedule, Traversers, Weighted,
+    };
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::TicTacToeSampler;
+    use bumpalo_herd::Herd;
+    use std::sync::Arc;
+
+    #[test]
+    fn weighted_traverses_in_proportion() {
+        let schedule = Weighted::new(vec![3.0, 1.0]);
+
+        let mut counts = [0; 2];
+        for iteration in 0..1000 {
+            let traversers = schedule.traversers(iteration, 0, 2);
+            for (player, count) in counts.iter_mut().enumerate() {
+                if traversers.contains(player) {
+                    *count += 1;
+                }
+            }
+        }
+
+        assert!((740..=760).contains(&counts[0]), "{:?}", counts);
+        assert_eq!(counts[0] + counts[1], 1000);
+        assert!(Traversers::all(2).contains_all(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "players can only be on one team")]
+    fn teams_cant_share_players() {
+        Team::new(vec![Traversers::all(2), Traversers::single(1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "every player needs to be on a team")]
+    fn teams_cover_every_player() {
+        Team::new(vec![Traversers::single(0).with(2)]).traversers(1, 0, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "weights can't be negative")]
+    fn weights_cant_be_negative() {
+        Weighted::new(vec![1.0, -1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "every player needs a weight")]
+    fn weights_cover_every_player() {
+        Weighted::new(vec![1.0]).traversers(1, 0, 2);
+    }
+
+    /// How many infosets got updated on each of the first two iterations
+    fn infosets_updated(schedule: impl TraverserSchedule + 'static) -> Vec<usize> {
+        let herd = Herd::new();
+        let observer = Arc::new(CollectStats::default());
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_batch_size(1)
+            .with_traversal(Traversal::Full)
+            .with_traverser_schedule(schedule)
+            .with_observer(observer.clone());
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board_with_win_for_x(),
+            },
+            2,
+        );
+
+        let stats = observer.0.lock();
+        stats.iter().map(|s| s.infosets_updated).collect()
+    }
+
+    #[test]
+    fn traversing_players_together_updates_them_together() {
+        let alternating = infosets_updated(Alternating);
+        let simultaneous = infosets_updated(Simultaneous);
+
+        // Both players' infosets get updated on every iteration, instead of one player's on each
+        assert_eq!(simultaneous[0], alternating[0] + alternating[1]);
+        assert_eq!(
+            infosets_updated(Team::new(vec![Traversers::all(2)])),
+            simultaneous
+        );
+    }
+}
@@ -1,35 +1,84 @@
-use crate::cfr::game_model::{Probability, VisibleInfo};
+use crate::cfr::game_model::{Probability, Utility, VisibleInfo};
+use crate::cfr::strategy_generation::cfr_config::CfrConfig;
+use crate::cfr::strategy_generation::pruning::RegretPruning;
 use crate::cfr::strategy_generation::workspace_data::StrategyGenerationProgress;
+use atomic_float::AtomicF64;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tinyvec::TinyVec;
 
-pub(crate) fn update_strategy_from_regret<INFO: VisibleInfo>(
+/// What changed over a strategy update, for [`IterationStats`](crate::cfr::strategy_generation::observer::IterationStats)
+pub(crate) struct StrategyUpdate {
+    pub(crate) infosets_updated: usize,
+    pub(crate) total_positive_regret: Utility,
+    pub(crate) average_strategy_change: Probability,
+}
+
+pub(crate) fn update_strategy_from_regret<INFO: VisibleInfo, CONFIG: CfrConfig>(
     strategy_generation_progress: &StrategyGenerationProgress<INFO>,
-) {
-    strategy_generation_progress.consume_updated_infosets(|i| {
-        let mut total_regret = 0.0;
-        let number_of_moves = i.moves().len();
+    config: &CONFIG,
+    pruning: Option<&RegretPruning>,
+    iteration: u32,
+) -> StrategyUpdate {
+    let infosets_updated = AtomicUsize::new(0);
+    let total_positive_regret = AtomicF64::new(0.0);
+    let total_strategy_change = AtomicF64::new(0.0);
 
-        // First we get the regret of every move, and write it in as a fake probability
-        // This is a hack to avoid having to store any intermediate values
-        for move_with_data in i.moves() {
-            total_regret += move_with_data.d.regret();
+    strategy_generation_progress.consume_updated_infosets(|i| {
+        // Infosets that only had their average strategy added to keep their current strategy
+        if !i.apply_move_updates(config, iteration) {
+            return;
         }
 
-        // Next we grab those stored regret values and divide them by the total regret
-        let mut total_probability = 0.0;
-        for move_with_data in i.moves() {
-            let new_probability = if total_regret > 0.0 {
-                move_with_data.d.regret() / total_regret
-            } else {
-                1.0 / number_of_moves as Probability
-            };
+        let last_discounted = i.swap_discounted_iteration(iteration);
+        let regrets: TinyVec<[Utility; 16]> = i
+            .moves()
+            .iter()
+            .map(|move_with_data| {
+                move_with_data
+                    .d
+                    .discount_regret(config, last_discounted, iteration);
+                config.regret_for_strategy(
+                    move_with_data.d.regret(),
+                    move_with_data.d.take_latest_regret(),
+                )
+            })
+            .collect();
 
+        let mut strategy: TinyVec<[Probability; 16]> = regrets.iter().map(|_| 0.0).collect();
+        config.strategy_from_regret(&regrets, &mut strategy);
+
+        let mut total_probability = 0.0;
+        let mut strategy_change = 0.0;
+        for (move_with_data, new_probability) in i.moves().iter().zip(strategy) {
             total_probability += new_probability;
+            strategy_change +=
+                (move_with_data.d.load_move_probability(i.move_count()) - new_probability).abs();
 
             debug_assert!(new_probability >= 0.0);
             debug_assert!(new_probability <= 1.0);
             debug_assert!(total_probability <= 1.1);
 
             move_with_data.d.write_move_probability(new_probability);
+
+            if let Some(pruning) = pruning {
+                move_with_data.d.track_pruning(pruning.threshold);
+            }
         }
+
+        let positive_regret: Utility = i.moves().iter().map(|m| m.d.regret().max(0.0)).sum();
+        infosets_updated.fetch_add(1, Ordering::Relaxed);
+        total_positive_regret.fetch_add(positive_regret, Ordering::Relaxed);
+        total_strategy_change.fetch_add(strategy_change, Ordering::Relaxed);
     });
+
+    let infosets_updated = infosets_updated.into_inner();
+    StrategyUpdate {
+        infosets_updated,
+        total_positive_regret: total_positive_regret.into_inner(),
+        average_strategy_change: if infosets_updated == 0 {
+            0.0
+        } else {
+            total_strategy_change.into_inner() / infosets_updated as Probability
+        },
+    }
 }
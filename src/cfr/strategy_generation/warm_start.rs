@@ -0,0 +1,157 @@
+use crate::cfr::game_model::{Probability, VisibleInfo};
+use crate::cfr::strategy_generation::fallback::{restrict_to, Heuristic, MoveProbabilities};
+use crate::cfr::strategy_generation::frozen_strategy::FrozenStrategy;
+use crate::cfr::strategy_generation::strategy::{Policy, Strategy};
+use crate::cfr::strategy_generation::workspace_data::data_for_infoset::DataForInfoSet;
+
+/// A strategy to start training from, instead of uniform play
+pub trait StrategyPrior<INFO: VisibleInfo>: Send + Sync {
+    /// How much weight to put on each move at `info`, or `None` to start it off uniform
+    ///
+    /// The weights don't need to add up to anything, they get scaled to add up to 1 over the
+    /// moves that are legal at `info`.
+    fn move_weights(&self, info: &INFO) -> Option<MoveProbabilities<INFO>>;
+}
+
+/// Starts from the average strategy of an earlier run
+impl<INFO: VisibleInfo> StrategyPrior<INFO> for Strategy<'_, INFO> {
+    fn move_weights(&self, info: &INFO) -> Option<MoveProbabilities<INFO>> {
+        self.lookup_move_probabilities(info, Policy::Average)
+            .map(|view| view.iter().map(|(m, p)| (*m, *p)).collect())
+    }
+}
+
+impl<INFO: VisibleInfo> StrategyPrior<INFO> for FrozenStrategy<INFO> {
+    fn move_weights(&self, info: &INFO) -> Option<MoveProbabilities<INFO>> {
+        self.get(info).map(|moves| moves.to_vec())
+    }
+}
+
+impl<INFO: VisibleInfo, F: Fn(&INFO) -> Option<MoveProbabilities<INFO>> + Send + Sync>
+    StrategyPrior<INFO> for Heuristic<F>
+{
+    fn move_weights(&self, info: &INFO) -> Option<MoveProbabilities<INFO>> {
+        (self.heuristic)(info)
+    }
+}
+
+/// Where the prior gets put when an infoset is first reached
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PriorMass {
+    /// As cumulative regret, so the current strategy starts out as the prior. Regret matching
+    /// moves away from it once real regret outweighs `confidence`, which is in units of utility
+    #[default]
+    Regret,
+    /// As average strategy mass, so the average strategy starts out as the prior. It's worth
+    /// `confidence` iterations of averaging weight, and play still starts out uniform
+    AverageStrategy,
+}
+
+/// Seeds every infoset with a [`StrategyPrior`] as it's first reached, so training doesn't have
+/// to start from uniform play
+pub struct WarmStart<'p, INFO: VisibleInfo> {
+    prior: Box<dyn StrategyPrior<INFO> + 'p>,
+    mass: PriorMass,
+    confidence: Probability,
+}
+
+impl<'p, INFO: VisibleInfo> WarmStart<'p, INFO> {
+    pub fn new(
+        prior: impl StrategyPrior<INFO> + 'p,
+        mass: PriorMass,
+        confidence: Probability,
+    ) -> Self {
+        assert!(confidence >= 0.0);
+
+        Self {
+            prior: Box::new(prior),
+            mass,
+            confidence,
+        }
+    }
+
+    pub(crate) fn apply(&self, info: &INFO, data_for_info_set: &DataForInfoSet<INFO>) {
+        if data_for_info_set.is_terminal() {
+            return;
+        }
+        let Some(weights) = self.prior.move_weights(info) else {
+            return;
+        };
+
+        let legal: Vec<INFO::Move> = data_for_info_set.moves().iter().map(|m| m.m).collect();
+        let probabilities = restrict_to::<INFO>(&legal, weights);
+        if probabilities.is_empty() {
+            return;
+        }
+
+        // `restrict_to` keeps the moves in the order they were given
+        for (move_with_data, (_, probability)) in
+            data_for_info_set.moves().iter().zip(probabilities)
+        {
+            match self.mass {
+                PriorMass::Regret => {
+                    move_with_data.d.seed_regret(self.confidence * probability);
+                    move_with_data.d.write_move_probability(probability);
+                }
+                PriorMass::AverageStrategy => move_with_data
+                    .d
+                    .accumulate_strategy(self.confidence * probability),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::fallback::Heuristic;
+    use crate::cfr::strategy_generation::strategy::Policy;
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::warm_start::{PriorMass, WarmStart};
+    use crate::tic_tac_toe::test::{board_with_win_for_x, WINNING_MOVE};
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use bumpalo_herd::Herd;
+
+    #[test]
+    fn warm_starting_from_a_prior_saves_training() {
+        let herd = Herd::new();
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+        let cold = StrategyGenerator::new(&herd);
+        let untrained = cold.exploitability(&sampler, Policy::Average).nash_conv();
+        cold.refine_strategy(sampler.clone(), 5);
+
+        let warm = StrategyGenerator::new(&herd).with_warm_start(WarmStart::new(
+            Heuristic {
+                heuristic: |_: &TicTacToeBoard| Some(vec![(WINNING_MOVE, 1.0)]),
+            },
+            PriorMass::Regret,
+            1.0,
+        ));
+        // Adding the infoset shows the prior training would start it from
+        #[allow(deprecated)]
+        let prior = warm.strategy_for_info(board_with_win_for_x(), Policy::Current);
+        assert_eq!(prior.move_probability(&WINNING_MOVE), 1.0);
+        warm.refine_strategy(sampler.clone(), 5);
+        assert!(
+            warm.exploitability(&sampler, Policy::Average).nash_conv()
+                < cold.exploitability(&sampler, Policy::Average).nash_conv()
+        );
+
+        // Average strategy mass gets played by the average strategy before any training
+        let from_strategy = StrategyGenerator::new(&herd).with_warm_start(WarmStart::new(
+            warm.into_strategy(),
+            PriorMass::AverageStrategy,
+            10.0,
+        ));
+        #[allow(deprecated)]
+        let prior = from_strategy.strategy_for_info(board_with_win_for_x(), Policy::Average);
+        assert!(prior.move_probability(&WINNING_MOVE) > 0.9);
+        assert!(
+            from_strategy
+                .exploitability(&sampler, Policy::Average)
+                .nash_conv()
+                < untrained / 2.0
+        );
+    }
+}
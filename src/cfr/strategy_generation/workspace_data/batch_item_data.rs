@@ -1,69 +1,95 @@
-use crate::cfr::game_model::UtilityForAllPlayers;
-use crate::cfr::strategy_generation::workspace_data::timestamp::{Timestamp, MAX_BATCH_SIZE};
+use crate::cfr::strategy_generation::workspace_data::timestamp::Timestamp;
 use parking_lot::{Mutex, MutexGuard};
 use std::sync::atomic::{AtomicU32, Ordering};
 
+/// Scratch storage for each item in a batch, so every item can be traversed at once
+///
+/// Each item gets `slots` values of its own, such as one for every move at an infoset.
 #[derive(Debug)]
 pub(crate) struct DataPerBatchItem<T> {
-    updated_iteration_per_work_item: [AtomicU32; MAX_BATCH_SIZE],
+    batch_size: usize,
+    slots: usize,
+    updated_iteration_per_work_item: Box<[AtomicU32]>,
     // Since batches are in practice each handled by a different thread, this mutex is uncontended
     // and should be nearly free
-    data: [Mutex<T>; MAX_BATCH_SIZE],
+    data: Box<[Mutex<T>]>,
 }
 
 impl<T> DataPerBatchItem<T> {
-    pub fn new(f: impl Fn() -> T) -> Self {
+    pub fn new(batch_size: usize, slots: usize, f: impl Fn() -> T) -> Self {
         Self {
-            updated_iteration_per_work_item: [const { AtomicU32::new(0) }; MAX_BATCH_SIZE],
-            data: [(); MAX_BATCH_SIZE].map(|_| Mutex::new(f())),
+            batch_size,
+            slots,
+            updated_iteration_per_work_item: (0..batch_size * slots)
+                .map(|_| AtomicU32::new(0))
+                .collect(),
+            data: (0..batch_size * slots).map(|_| Mutex::new(f())).collect(),
         }
     }
 
+    fn index(&self, timestamp: Timestamp, slot: usize) -> usize {
+        debug_assert!(slot < self.slots);
+        timestamp.item_within_iteration * self.slots + slot
+    }
+
     pub fn set(&self, value: T, timestamp: Timestamp) {
-        self.updated_iteration_per_work_item[timestamp.item_within_iteration]
+        self.set_slot(0, value, timestamp)
+    }
+
+    pub fn get(&self, timestamp: Timestamp) -> Option<MutexGuard<'_, T>> {
+        self.get_slot(0, timestamp)
+    }
+
+    pub fn ready(&self, timestamp: Timestamp) -> bool {
+        self.slot_ready(0, timestamp)
+    }
+
+    pub fn set_slot(&self, slot: usize, value: T, timestamp: Timestamp) {
+        let index = self.index(timestamp, slot);
+        self.updated_iteration_per_work_item[index]
             .store(timestamp.cfr_iteration, Ordering::Relaxed);
-        *self.data[timestamp.item_within_iteration].lock() = value
+        *self.data[index].lock() = value
     }
 
-    pub fn get(&self, timestamp: Timestamp) -> Option<MutexGuard<T>> {
-        if !self.ready(timestamp) {
+    pub fn get_slot(&self, slot: usize, timestamp: Timestamp) -> Option<MutexGuard<'_, T>> {
+        if !self.slot_ready(slot, timestamp) {
             None
         } else {
-            Some(self.data[timestamp.item_within_iteration].lock())
+            Some(self.data[self.index(timestamp, slot)].lock())
         }
     }
 
-    pub fn ready(&self, timestamp: Timestamp) -> bool {
-        self.updated_iteration_per_work_item[timestamp.item_within_iteration]
-            .load(Ordering::Relaxed)
+    pub fn slot_ready(&self, slot: usize, timestamp: Timestamp) -> bool {
+        self.updated_iteration_per_work_item[self.index(timestamp, slot)].load(Ordering::Relaxed)
             >= timestamp.cfr_iteration
     }
-}
 
-impl DataPerBatchItem<UtilityForAllPlayers> {
-    pub const fn const_default_utility() -> Self {
-        Self {
-            updated_iteration_per_work_item: [const { AtomicU32::new(0) }; MAX_BATCH_SIZE],
-            data: [const { Mutex::new(UtilityForAllPlayers::const_default()) }; MAX_BATCH_SIZE],
+    /// Changes this batch item's value in `slot`, starting from the default if it hasn't been set
+    /// yet this iteration
+    pub fn update_slot(&self, slot: usize, timestamp: Timestamp, f: impl FnOnce(&mut T))
+    where
+        T: Default,
+    {
+        let index = self.index(timestamp, slot);
+        let mut value = self.data[index].lock();
+        if self.updated_iteration_per_work_item[index]
+            .swap(timestamp.cfr_iteration, Ordering::Relaxed)
+            < timestamp.cfr_iteration
+        {
+            *value = T::default();
         }
-    }
-}
 
-impl<T> DataPerBatchItem<Option<T>> {
-    pub const fn const_default_option() -> Self {
-        Self {
-            updated_iteration_per_work_item: [const { AtomicU32::new(0) }; MAX_BATCH_SIZE],
-            data: [const { Mutex::new(None) }; MAX_BATCH_SIZE],
-        }
+        f(&mut value)
     }
-}
 
-impl<T> Default for DataPerBatchItem<T>
-where
-    T: Default,
-    T: Clone,
-{
-    fn default() -> Self {
-        Self::new(Default::default)
+    /// The value in `slot` of every batch item that set it on `cfr_iteration`, in batch item
+    /// order, so adding them up always rounds the same way
+    pub fn slot_values(
+        &self,
+        slot: usize,
+        cfr_iteration: u32,
+    ) -> impl Iterator<Item = MutexGuard<'_, T>> {
+        (0..self.batch_size)
+            .filter_map(move |item| self.get_slot(slot, Timestamp::new(cfr_iteration, item)))
     }
 }
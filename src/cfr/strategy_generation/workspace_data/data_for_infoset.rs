@@ -1,13 +1,15 @@
-use crate::cfr::game_model::{PlayerNumber, Probability, UtilityForAllPlayers, VisibleInfo};
+use crate::cfr::game_model::{
+    PlayerNumber, Probability, Utility, UtilityForAllPlayers, VisibleInfo,
+};
+use crate::cfr::strategy_generation::cfr_config::CfrConfig;
 use crate::cfr::strategy_generation::workspace_data::batch_item_data::DataPerBatchItem;
 use crate::cfr::strategy_generation::workspace_data::move_data::{
     MoveWithData, MoveWithDataAllocation,
 };
 use crate::cfr::strategy_generation::workspace_data::timestamp::Timestamp;
 use atomic_float::AtomicF64;
-use rustc_hash::FxHasher;
-use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicU32, Ordering};
+use fastrand::Rng;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 #[derive(Debug)]
 pub(crate) struct DataForInfoSet<INFO: VisibleInfo> {
@@ -17,33 +19,50 @@ pub(crate) struct DataForInfoSet<INFO: VisibleInfo> {
     counterfactual_n: AtomicF64,
     // FIXME: Decide if we need this
     // cumulative_counterfactual: Mutex<UtilityForAllPlayers>,
-    counterfactual_for_current_iteration: DataPerBatchItem<UtilityForAllPlayers>,
+    // What each batch item adds to each move, kept apart so they can be added up in the same order
+    // whichever threads they ran on. One slot per move, in the same order as `move_data`
+    move_updates: DataPerBatchItem<MoveUpdate>,
+    // The last iteration that added any regret here
+    regret_iteration: AtomicU32,
 
     // Use this to figure out if we need to write into the `updated_infosets` SegQueue
     global_updated_iteration: AtomicU32,
+    // Lets infosets that have stopped being reached get discarded
+    last_visited_iteration: AtomicU32,
+    // The last strategy update that discounted the regret here, so the iterations in between that
+    // didn't reach this infoset can be caught up on. Zero if it's never been discounted
+    discounted_iteration: AtomicU32,
+    // How many times traversals have reached this infoset, for telling apart well trained infosets
+    visits: AtomicU64,
 
     move_data: Vec<MoveWithData<INFO>>,
 }
 
 impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
     #[inline]
-    pub(crate) fn new(info: &INFO) -> Self {
+    pub(crate) fn new(info: &INFO, batch_size: usize) -> Self {
         let mut move_data = MoveWithDataAllocation::new();
 
         let terminal_utility = info.run_for_moves(|m| {
             move_data.push(m);
         });
 
+        let move_data = move_data.into_vec();
+
         Self {
             turn_player: info.turn(),
             terminal_utility,
-            move_data: move_data.into_vec(),
 
             counterfactual_n: AtomicF64::new(0.0),
             // cumulative_counterfactual: const { Mutex::new(UtilityForAllPlayers::const_default()) },
-            counterfactual_for_current_iteration: const { DataPerBatchItem::const_default_utility() },
+            move_updates: DataPerBatchItem::new(batch_size, move_data.len(), Default::default),
+            regret_iteration: AtomicU32::new(0),
+            move_data,
 
             global_updated_iteration: AtomicU32::new(0),
+            last_visited_iteration: AtomicU32::new(0),
+            discounted_iteration: AtomicU32::new(0),
+            visits: AtomicU64::new(0),
         }
     }
 
@@ -51,38 +70,9 @@ impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
         self.turn_player
     }
 
-    pub(crate) fn is_ready(&self, timestamp: Timestamp) -> bool {
-        self.counterfactual_for_current_iteration.ready(timestamp)
-    }
-
-    pub(crate) fn ready_with_counterfactual(
-        &self,
-        util: UtilityForAllPlayers,
-        gamestate_probablity: Probability,
-        timestamp: Timestamp,
-    ) {
-        // FIXME: Clean this up a bit
-
-        // let mut cumulative_counterfactual = self.cumulative_counterfactual.lock();
-        // let prev_n = self.counterfactual_n.fetch_add(1.0, Ordering::Relaxed);
-        // cumulative_counterfactual.reduce(prev_n);
-        // // TODO: Does this really make sense in games where different gamestates have different probabilities?
-        // cumulative_counterfactual.accumulate(&util, gamestate_probablity);
-        // // cumulative_counterfactual.accumulate(&util, 1.0);
-        // cumulative_counterfactual.reduce(1.0 / (prev_n + 1.0));
-
-        // let mut cumulative_counterfactual = self.cumulative_counterfactual.lock();
-        // let _prev_n = self.counterfactual_n.fetch_add(1.0, Ordering::Relaxed);
-        // // cumulative_counterfactual.reduce(prev_n);
-        // // TODO: Does this really make sense in games where different gamestates have different probabilities?
-        // cumulative_counterfactual.accumulate(&util, gamestate_probablity);
-        // // cumulative_counterfactual.accumulate(&util, 1.0);
-        // // cumulative_counterfactual.reduce(1.0 / (prev_n + 1.0));
-
-        let mut util = util;
-        util.reduce(gamestate_probablity);
-        self.counterfactual_for_current_iteration
-            .set(util, timestamp);
+    /// Counts a traversal getting to the end of this infoset, once for each path it took there
+    pub(crate) fn record_visit(&self) {
+        self.visits.fetch_add(1, Ordering::Relaxed);
     }
 
     pub(crate) fn move_count(&self) -> usize {
@@ -93,18 +83,59 @@ impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
         &self.move_data
     }
 
-    pub(crate) fn increment_terminal_counterfactual(
+    /// Adds the current strategy into the running average strategy, scaled by `weight`, once
+    /// [`Self::apply_move_updates`] gets to it
+    pub(crate) fn accumulate_average_strategy(&self, weight: Probability, timestamp: Timestamp) {
+        let n_moves = self.moves().len();
+
+        for (move_index, m) in self.move_data.iter().enumerate() {
+            let probability = m.d.load_move_probability(n_moves);
+            self.move_updates
+                .update_slot(move_index, timestamp, |update| {
+                    update.strategy += weight * probability
+                });
+        }
+    }
+
+    /// Adds to the instantaneous regret of the move at `move_index` for this batch item, for
+    /// [`Self::apply_move_updates`] to clip and add to the cumulative regret
+    pub(crate) fn accumulate_regret(
         &self,
-        gamestate_probablity: Probability,
+        move_index: usize,
+        instant_regret: Utility,
         timestamp: Timestamp,
-    ) -> bool {
-        match &self.terminal_utility {
-            Some(x) => {
-                self.ready_with_counterfactual(*x, gamestate_probablity, timestamp);
-                true
+    ) {
+        self.regret_iteration
+            .store(timestamp.cfr_iteration, Ordering::Relaxed);
+        self.move_updates
+            .update_slot(move_index, timestamp, |update| {
+                update.regret += instant_regret
+            });
+    }
+
+    /// Only called between iterations. Adds up what every batch item added to each move over
+    /// `iteration`, in batch item order. Returns whether any regret was added
+    ///
+    /// Each batch item's instantaneous regret is clipped as a whole, after every path it took
+    /// into this infoset has added to it.
+    pub(crate) fn apply_move_updates(&self, config: &impl CfrConfig, iteration: u32) -> bool {
+        for (move_index, m) in self.move_data.iter().enumerate() {
+            let mut total = MoveUpdate::default();
+            for update in self.move_updates.slot_values(move_index, iteration) {
+                total.regret += config
+                    .weigh_instant_regret(config.clip_instant_regret(update.regret), iteration);
+                total.strategy += update.strategy;
             }
-            None => false,
+
+            m.d.add_regret(total.regret);
+            m.d.accumulate_strategy(total.strategy);
         }
+
+        self.regret_iteration.load(Ordering::Relaxed) == iteration
+    }
+
+    pub(crate) fn terminal_utility(&self) -> Option<UtilityForAllPlayers> {
+        self.terminal_utility
     }
 
     pub(crate) fn check_for_writeback(&self, timestamp: Timestamp) -> bool {
@@ -118,13 +149,44 @@ impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
         old_iter != current_iteration
     }
 
-    pub(crate) fn get_iteration_utility_if_ready(
-        &self,
-        timestamp: Timestamp,
-    ) -> Option<UtilityForAllPlayers> {
-        self.counterfactual_for_current_iteration
-            .get(timestamp)
-            .map(|x| *x)
+    pub(crate) fn mark_as_visited(&self, timestamp: Timestamp) {
+        self.last_visited_iteration
+            .store(timestamp.cfr_iteration, Ordering::Relaxed);
+    }
+
+    pub(crate) fn last_visited_iteration(&self) -> u32 {
+        self.last_visited_iteration.load(Ordering::Relaxed)
+    }
+
+    /// Only called when restoring a checkpoint. Infosets that were never visited stay that way
+    pub(crate) fn restore_last_visited_iteration(&self, iteration: u32) {
+        self.last_visited_iteration
+            .store(iteration, Ordering::Relaxed);
+    }
+
+    /// Only called between iterations. Records that the regret is being discounted on `iteration`,
+    /// returning the last iteration it was discounted on before
+    pub(crate) fn swap_discounted_iteration(&self, iteration: u32) -> u32 {
+        self.discounted_iteration.swap(iteration, Ordering::Relaxed)
+    }
+
+    pub(crate) fn discounted_iteration(&self) -> u32 {
+        self.discounted_iteration.load(Ordering::Relaxed)
+    }
+
+    /// Only called when restoring a checkpoint
+    pub(crate) fn restore_discounted_iteration(&self, iteration: u32) {
+        self.discounted_iteration
+            .store(iteration, Ordering::Relaxed);
+    }
+
+    pub(crate) fn visits(&self) -> u64 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    /// Only called when restoring a checkpoint
+    pub(crate) fn restore_visits(&self, visits: u64) {
+        self.visits.store(visits, Ordering::Relaxed);
     }
 
     // pub(crate) fn get_cumulative_counterfactual(&self) -> UtilityForAllPlayers {
@@ -135,16 +197,16 @@ impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
         self.terminal_utility.is_some()
     }
 
+    /// Samples a move, mixing the current strategy with a uniform one by `exploration`
+    ///
+    /// Deterministic for a given `rng`, see [`Timestamp::rng`]. Returns the move, the probability
+    /// of the current strategy playing it and the probability it was sampled with
     pub(crate) fn sample_move_deterministic(
         &self,
-        gamestate: &INFO::Gamestate,
-        timestamp: Timestamp,
-    ) -> INFO::Move {
-        let mut seed_builder = FxHasher::default();
-        Hash::hash(gamestate, &mut seed_builder);
-        Hash::hash(&timestamp, &mut seed_builder);
-
-        let mark = fastrand::Rng::with_seed(seed_builder.finish()).f64();
+        mut rng: Rng,
+        exploration: Probability,
+    ) -> (INFO::Move, Probability, Probability) {
+        let mark = rng.f64();
 
         let mut cumulative = 0.0;
 
@@ -152,10 +214,11 @@ impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
 
         for m in &self.move_data {
             let prob = m.d.load_move_probability(n_moves);
-            cumulative += prob;
+            let sampling_prob = exploration / n_moves as Probability + (1.0 - exploration) * prob;
+            cumulative += sampling_prob;
 
             if mark < cumulative {
-                return m.m;
+                return (m.m, prob, sampling_prob);
             }
         }
 
@@ -163,3 +226,11 @@ impl<INFO: VisibleInfo> DataForInfoSet<INFO> {
         panic!("Move probabilities did not sum to 1.0!")
     }
 }
+
+/// What a single batch item adds to a move over an iteration
+#[derive(Debug, Default, Clone, Copy)]
+struct MoveUpdate {
+    // Instantaneous, before it's clipped and weighed
+    regret: Utility,
+    strategy: Probability,
+}
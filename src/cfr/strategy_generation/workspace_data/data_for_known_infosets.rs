@@ -1,5 +1,7 @@
 use crate::cfr::game_model::VisibleInfo;
+use crate::cfr::strategy_generation::warm_start::WarmStart;
 use crate::cfr::strategy_generation::workspace_data::data_for_infoset::DataForInfoSet;
+use crate::cfr::strategy_generation::workspace_data::timestamp::DEFAULT_BATCH_SIZE;
 use bumpalo_herd::Member;
 use dashmap::{DashMap, Entry};
 use rustc_hash::FxHasher;
@@ -7,9 +9,32 @@ use std::hash::BuildHasherDefault;
 
 pub(crate) struct DataForKnownInfosets<'h, INFO: VisibleInfo> {
     infoset_data: DashMap<INFO, &'h DataForInfoSet<INFO>, BuildHasherDefault<FxHasher>>,
+    // How many batch items new infosets need scratch space for
+    batch_size: usize,
+    // Seeds new infosets
+    warm_start: Option<WarmStart<'h, INFO>>,
 }
 
 impl<'h, INFO: VisibleInfo> DataForKnownInfosets<'h, INFO> {
+    pub(crate) fn new(batch_size: usize) -> Self {
+        Self {
+            infoset_data: Default::default(),
+            batch_size,
+            warm_start: None,
+        }
+    }
+
+    /// Only infosets created from then on are affected
+    pub(crate) fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size;
+    }
+
+    /// Only infosets created from then on are affected
+    pub(crate) fn set_warm_start(&mut self, warm_start: WarmStart<'h, INFO>) {
+        self.warm_start = Some(warm_start);
+    }
+
+    /// Creates the data with a uniform strategy if `info` hasn't been seen before
     pub(crate) fn data_for_infoset(
         &self,
         info: INFO,
@@ -18,7 +43,10 @@ impl<'h, INFO: VisibleInfo> DataForKnownInfosets<'h, INFO> {
         match self.infoset_data.entry(info) {
             Entry::Occupied(a) => a.get(),
             Entry::Vacant(v) => {
-                let h = &*member.alloc_with(|| DataForInfoSet::new(v.key()));
+                let h = &*member.alloc_with(|| DataForInfoSet::new(v.key(), self.batch_size));
+                if let Some(warm_start) = &self.warm_start {
+                    warm_start.apply(v.key(), h);
+                }
 
                 v.insert(h);
                 h
@@ -44,12 +72,49 @@ impl<'h, INFO: VisibleInfo> DataForKnownInfosets<'h, INFO> {
         //
         // self.infoset_data.shards().get(&info).cloned().unwrap_or_default()
     }
+
+    /// Like [`Self::data_for_infoset`], but never creates anything
+    pub(crate) fn get(&self, info: &INFO) -> Option<&'h DataForInfoSet<INFO>> {
+        self.infoset_data.get(info).map(|data| *data)
+    }
+}
+
+impl<INFO: VisibleInfo> DataForKnownInfosets<'_, INFO> {
+    pub(crate) fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.infoset_data.len()
+    }
+
+    /// Nothing else should be creating infosets meanwhile
+    pub(crate) fn for_each(&self, mut f: impl FnMut(&INFO, &DataForInfoSet<INFO>)) {
+        for entry in self.infoset_data.iter() {
+            f(entry.key(), entry.value());
+        }
+    }
+
+    /// Stops at the first error. Nothing else should be creating infosets meanwhile
+    pub(crate) fn try_for_each<E>(
+        &self,
+        mut f: impl FnMut(&INFO, &DataForInfoSet<INFO>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        for entry in self.infoset_data.iter() {
+            f(entry.key(), entry.value())?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets every infoset `f` returns false for. The data itself stays allocated in the herd
+    pub(crate) fn retain(&self, f: impl Fn(&DataForInfoSet<INFO>) -> bool) {
+        self.infoset_data.retain(|_, data| f(data));
+    }
 }
 
 impl<'h, INFO: VisibleInfo> Default for DataForKnownInfosets<'h, INFO> {
     fn default() -> Self {
-        Self {
-            infoset_data: Default::default(),
-        }
+        Self::new(DEFAULT_BATCH_SIZE)
     }
 }
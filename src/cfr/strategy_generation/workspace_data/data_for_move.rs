@@ -1,40 +1,48 @@
-use crate::cfr::game_model::{
-    AtomicProbability, AtomicUtility, OracleGamestate, Probability, Utility, UtilityForAllPlayers,
-    VisibleInfo,
-};
-use crate::cfr::strategy_generation::workspace_data::batch_item_data::DataPerBatchItem;
-use crate::cfr::strategy_generation::workspace_data::data_for_infoset::DataForInfoSet;
-use crate::cfr::strategy_generation::workspace_data::timestamp::Timestamp;
-use crate::cfr::strategy_generation::workspace_data::StrategyGenerationProgress;
-use bumpalo_herd::Member;
-use std::sync::atomic::Ordering;
+use crate::cfr::game_model::{AtomicProbability, AtomicUtility, Probability, Utility};
+use crate::cfr::strategy_generation::cfr_config::CfrConfig;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Everything about a move that carries over from one iteration to the next
+///
+/// The regret added over an iteration is left out, since it's always taken by the strategy
+/// update before the iteration ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MoveCheckpoint {
+    pub(crate) regret: Utility,
+    // As stored, so zero and NaN keep their special meanings
+    pub(crate) move_probability: Probability,
+    pub(crate) strategy_sum: Probability,
+    pub(crate) updates_below_threshold: u64,
+}
 
 #[derive(Debug)]
 pub(crate) struct DataForMove {
     move_selection_probability: AtomicProbability,
-    // Since multiple work threads can exist at a time, we need dedicated storage per batch item
+    // Shared by the whole batch. Batch items add their regret to their own slots in the infoset's
+    // move updates, which only get added in here between iterations
     cumulative_move_regret: AtomicUtility,
-    utility_after_move: DataPerBatchItem<UtilityForAllPlayers>,
-    // cached_post_move_infoset:
-    //     DataPerBatchItem<Option<(Arc<DataForInfoSet<INFO>>, Arc<INFO::Gamestate>)>>,
-    // TODO: Can we consoldiate the previous two?
+    // Regret added over the current iteration, for configs that use it to predict the next one
+    latest_move_regret: AtomicUtility,
+    // Reach-weighted sum of every strategy this move has been played with. Normalized, this is
+    // the average strategy, which is what actually converges to an equilibrium
+    cumulative_strategy: AtomicProbability,
+    // How many strategy updates in a row the regret has stayed below the pruning threshold. 64 bits
+    // wide so there's no padding, which would break zero-initializing this
+    updates_below_threshold: AtomicU64,
 }
 
 impl DataForMove {
     pub const fn new() -> Self {
         Self {
             cumulative_move_regret: AtomicProbability::new(0.0),
+            latest_move_regret: AtomicUtility::new(0.0),
+            cumulative_strategy: AtomicProbability::new(0.0),
+            updates_below_threshold: AtomicU64::new(0),
             // Zero on first iteration. NaN if the probability is actually zero
             move_selection_probability: AtomicProbability::new(0.0),
-            utility_after_move: const { DataPerBatchItem::const_default_utility() },
-            // cached_post_move_infoset: const { DataPerBatchItem::const_default_option() },
         }
     }
 
-    pub fn ready(&self, timestamp: Timestamp) -> bool {
-        self.utility_after_move.ready(timestamp)
-    }
-
     pub fn load_move_probability(&self, n_moves: usize) -> Probability {
         let res = self.move_selection_probability.load(Ordering::Relaxed);
 
@@ -62,82 +70,104 @@ impl DataForMove {
         }
     }
 
-    pub fn utility_after_move(&self) -> &DataPerBatchItem<UtilityForAllPlayers> {
-        &self.utility_after_move
+    pub fn accumulate_strategy(&self, weighted_probability: Probability) {
+        self.cumulative_strategy
+            .fetch_add(weighted_probability, Ordering::Relaxed);
     }
 
-    pub fn get_post_move_infoset<'h, INFO: VisibleInfo>(
-        &self,
-        strategy_generation_progress: &StrategyGenerationProgress<'h, INFO>,
-        member: &Member<'h>,
-        gamestate_before_move: &INFO::Gamestate,
-        m: &INFO::Move,
-    ) -> (&'h DataForInfoSet<INFO>, INFO::Gamestate) {
-        // if let Some(d) = self.cached_post_move_infoset.get(timestamp) {
-        //     return d.as_ref().unwrap().clone();
-        // }
+    pub fn strategy_sum(&self) -> Probability {
+        self.cumulative_strategy.load(Ordering::Relaxed)
+    }
+
+    /// Only called between iterations, with everything the batch items added up
+    pub fn add_regret(&self, weighted_regret: Utility) {
+        self.cumulative_move_regret
+            .fetch_add(weighted_regret, Ordering::Relaxed);
+        self.latest_move_regret
+            .fetch_add(weighted_regret, Ordering::Relaxed);
+    }
 
-        let state_after_move = gamestate_before_move.advance(m);
-        let info_after_move = state_after_move.info_for_turn_player();
-        let data_for_info_after_move =
-            strategy_generation_progress.get_data_for_infoset(info_after_move, member);
+    /// Only called on a move no iteration has reached yet
+    pub fn seed_regret(&self, regret: Utility) {
+        self.cumulative_move_regret.store(regret, Ordering::Relaxed);
+    }
 
-        // self.cached_post_move_infoset.set(
-        //     Some((data_for_info_after_move.clone(), state_after_move.clone())),
-        //     timestamp,
-        // );
+    pub fn regret(&self) -> Utility {
+        self.cumulative_move_regret.load(Ordering::Relaxed)
+    }
 
-        (data_for_info_after_move, state_after_move)
+    /// Only called between iterations. Returns the regret added over the iteration, and resets it
+    /// for the next one
+    pub fn take_latest_regret(&self) -> Utility {
+        self.latest_move_regret.swap(0.0, Ordering::Relaxed)
     }
 
-    pub fn accumulate_regret<'h, INFO: VisibleInfo>(
+    /// Only called between iterations, when nothing else is touching the regret
+    ///
+    /// Infosets only get updated on iterations that reach them, so the discounts for iterations
+    /// since `last_discounted` that didn't are applied first, before the regret added on
+    /// `iteration`. A `last_discounted` of zero means there's nothing to catch up on.
+    pub fn discount_regret<CONFIG: CfrConfig>(
         &self,
-        strategy_generation_progress: &StrategyGenerationProgress<'h, INFO>,
-        member: &Member<'h>,
-        timestamp: Timestamp,
-        pre_move_info: &DataForInfoSet<INFO>,
-        pre_move_gamestate: &INFO::Gamestate,
-        m: &INFO::Move,
+        config: &CONFIG,
+        last_discounted: u32,
+        iteration: u32,
     ) {
-        let turn = pre_move_info.turn();
-        let counterfactual_before = pre_move_info
-            .get_iteration_utility_if_ready(timestamp)
-            .unwrap();
-
-        let info_after = pre_move_gamestate.advance(m).info_for_turn_player();
-        let counterfactual_after = strategy_generation_progress
-            .get_data_for_infoset(info_after, member)
-            .get_iteration_utility_if_ready(timestamp)
-            .unwrap();
-
-        // let counterfactual_after = self
-        //     .cached_post_move_infoset
-        //     .get(timestamp)
-        //     .and_then(|a| {
-        //         a.as_ref()
-        //             .map(|(data, _)| data.get_cumulative_counterfactual().clone())
-        //     })
-        //     .expect("You can only accumulate regret when children are ready");
-
-        let new_positive_regret =
-            (counterfactual_after.get(turn) - counterfactual_before.get(turn)).max(0.0);
-
-        // FIXME: Make this configurable
-        let weighted_regret = new_positive_regret * timestamp.cfr_iteration as Utility;
+        let mut regret = self.regret();
+        if last_discounted > 0 && last_discounted + 1 < iteration {
+            let latest = self.latest_move_regret.load(Ordering::Relaxed);
+            let mut earlier = regret - latest;
+            for missed in last_discounted + 1..iteration {
+                earlier = config.discount_cumulative_regret(earlier, missed);
+            }
+            regret = earlier + latest;
+        }
 
+        let discounted = config.discount_cumulative_regret(regret, iteration);
         self.cumulative_move_regret
-            .fetch_add(weighted_regret, Ordering::Relaxed);
+            .store(discounted, Ordering::Relaxed);
     }
 
-    pub fn regret(&self) -> Utility {
-        self.cumulative_move_regret.load(Ordering::Relaxed)
+    /// Only called between iterations, after the regret is updated
+    pub fn track_pruning(&self, threshold: Utility) {
+        if self.regret() < threshold {
+            self.updates_below_threshold.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.updates_below_threshold.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn updates_below_threshold(&self) -> u64 {
+        self.updates_below_threshold.load(Ordering::Relaxed)
+    }
+
+    /// Only called between iterations
+    pub fn checkpoint(&self) -> MoveCheckpoint {
+        MoveCheckpoint {
+            regret: self.regret(),
+            move_probability: self.load_move_probability_unchecked(),
+            strategy_sum: self.strategy_sum(),
+            updates_below_threshold: self.updates_below_threshold(),
+        }
+    }
+
+    /// Only called between iterations
+    pub fn restore(&self, checkpoint: MoveCheckpoint) {
+        self.cumulative_move_regret
+            .store(checkpoint.regret, Ordering::Relaxed);
+        self.move_selection_probability
+            .store(checkpoint.move_probability, Ordering::Relaxed);
+        self.cumulative_strategy
+            .store(checkpoint.strategy_sum, Ordering::Relaxed);
+        self.updates_below_threshold
+            .store(checkpoint.updates_below_threshold, Ordering::Relaxed);
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::cfr::strategy_generation::cfr_config::DiscountedCfr;
     use crate::cfr::strategy_generation::workspace_data::data_for_move::DataForMove;
-    use std::mem;
 
     #[test]
     fn empty_is_zeroed() {
@@ -145,7 +175,6 @@ mod test {
         let struct_size = size_of::<DataForMove>();
 
         let struct_pointer = (&empty) as *const DataForMove as *const u8;
-        mem::forget(empty);
 
         let bytes = unsafe { std::slice::from_raw_parts(struct_pointer, struct_size) };
 
@@ -153,4 +182,25 @@ mod test {
             assert_eq!(*b, 0, "Testing byte {}", i);
         }
     }
+
+    #[test]
+    fn missed_discounts_are_caught_up() {
+        // Discounts by t / (t + 1) after iteration t, whatever the sign
+        let config = DiscountedCfr {
+            alpha: 1.0,
+            beta: 1.0,
+            gamma: 1.0,
+        };
+
+        let caught_up = DataForMove::new();
+        caught_up.seed_regret(4.0);
+        caught_up.discount_regret(&config, 1, 4);
+        // Iterations 2 and 3 were missed, then iteration 4 is discounted as usual
+        assert!((caught_up.regret() - 4.0 * 2.0 / 3.0 * 3.0 / 4.0 * 4.0 / 5.0).abs() < 1e-12);
+
+        let never_discounted = DataForMove::new();
+        never_discounted.seed_regret(-4.0);
+        never_discounted.discount_regret(&config, 0, 4);
+        assert!((never_discounted.regret() - -4.0 * 4.0 / 5.0).abs() < 1e-12);
+    }
 }
@@ -1,38 +1,46 @@
-use crate::cfr::game_model::VisibleInfo;
+use crate::cfr::game_model::{Probability, UtilityForAllPlayers, VisibleInfo};
 use crate::cfr::strategy_generation::workspace_data::data_for_infoset::DataForInfoSet;
 use crate::cfr::strategy_generation::workspace_data::data_for_known_infosets::DataForKnownInfosets;
+use crate::cfr::strategy_generation::workspace_data::reach_probabilities::ReachProbabilities;
 use crate::cfr::strategy_generation::workspace_data::timestamp::Timestamp;
 use bumpalo_herd::Member;
 use crossbeam::queue::SegQueue;
 use std::cell::{RefCell, RefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use thread_local::ThreadLocal;
 
 pub(crate) mod batch_item_data;
 pub(crate) mod data_for_infoset;
 pub(crate) mod data_for_known_infosets;
 pub(crate) mod data_for_move;
-mod move_data;
+pub(crate) mod move_data;
+pub(crate) mod reach_probabilities;
 pub(crate) mod timestamp;
 
 pub(crate) struct StrategyGenerationProgress<'h, INFO: VisibleInfo> {
     data_for_known_infosets: DataForKnownInfosets<'h, INFO>,
     thread_local_workstack: ThreadLocal<RefCell<ThreadLocalWorkStack<'h, INFO>>>,
     updated_infosets: SegQueue<&'h DataForInfoSet<INFO>>,
+    peak_workstack_depth: AtomicUsize,
+    moves_pruned: AtomicUsize,
 }
 
 impl<'h, INFO: VisibleInfo> StrategyGenerationProgress<'h, INFO> {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(batch_size: usize) -> Self {
         Self {
-            data_for_known_infosets: DataForKnownInfosets::default(),
+            data_for_known_infosets: DataForKnownInfosets::new(batch_size),
             thread_local_workstack: ThreadLocal::new(),
             updated_infosets: Default::default(),
+            peak_workstack_depth: AtomicUsize::new(0),
+            moves_pruned: AtomicUsize::new(0),
         }
     }
 
-    pub(crate) fn thread_local_workstack(&self) -> RefMut<ThreadLocalWorkStack<'h, INFO>> {
+    pub(crate) fn thread_local_workstack(&self) -> RefMut<'_, ThreadLocalWorkStack<'h, INFO>> {
         self.thread_local_workstack.get_or_default().borrow_mut()
     }
 
+    /// Queues the infoset for the strategy update at the end of the iteration, once per iteration
     pub(crate) fn mark_as_completed(
         &self,
         data_for_info_set: &'h DataForInfoSet<INFO>,
@@ -51,6 +59,47 @@ impl<'h, INFO: VisibleInfo> StrategyGenerationProgress<'h, INFO> {
         self.data_for_known_infosets.data_for_infoset(data, member)
     }
 
+    pub(crate) fn infosets_mut(&mut self) -> &mut DataForKnownInfosets<'h, INFO> {
+        &mut self.data_for_known_infosets
+    }
+
+    pub(crate) fn batch_size(&self) -> usize {
+        self.data_for_known_infosets.batch_size()
+    }
+
+    pub(crate) fn known_infosets(&self) -> usize {
+        self.data_for_known_infosets.len()
+    }
+
+    pub(crate) fn infosets(&self) -> &DataForKnownInfosets<'h, INFO> {
+        &self.data_for_known_infosets
+    }
+
+    pub(crate) fn record_workstack_depth(&self, depth: usize) {
+        self.peak_workstack_depth
+            .fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// The deepest any workstack got since this was last called
+    pub(crate) fn take_peak_workstack_depth(&self) -> usize {
+        self.peak_workstack_depth.swap(0, Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_moves_pruned(&self, moves_pruned: usize) {
+        self.moves_pruned.fetch_add(moves_pruned, Ordering::Relaxed);
+    }
+
+    /// How many moves traversals skipped since this was last called
+    pub(crate) fn take_moves_pruned(&self) -> usize {
+        self.moves_pruned.swap(0, Ordering::Relaxed)
+    }
+
+    /// Forgets every infoset that no traversal has reached since `iteration`
+    pub(crate) fn discard_infosets_not_visited_since(&self, iteration: u32) {
+        self.data_for_known_infosets
+            .retain(|data| data.last_visited_iteration() >= iteration);
+    }
+
     #[inline]
     pub(crate) fn consume_updated_infosets(
         &self,
@@ -68,21 +117,69 @@ impl<'h, INFO: VisibleInfo> StrategyGenerationProgress<'h, INFO> {
     }
 }
 
+/// Where the utility of a gamestate goes once it's known: towards the move at `move_index` of the
+/// gamestate before it, counting `weight` times
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct UtilityDestination {
+    pub(crate) move_index: usize,
+    pub(crate) weight: Probability,
+}
+
+/// How the moves of a gamestate waiting on its utilities were picked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PickedMoves {
+    /// Every move that isn't pruned
+    Expanded,
+    /// A single move, sampled from the current strategy
+    Sampled,
+    /// A single move, sampled with exploration
+    Explored {
+        move_index: usize,
+        move_probability: Probability,
+        sampling_probability: Probability,
+    },
+}
+
+/// A gamestate on the workstack
+///
+/// Every path through the game tree gets items of its own, even when it ends up somewhere another
+/// path already went. The utility below a gamestate can depend on how it was reached, and the
+/// regret always does.
+#[derive(Debug)]
+pub(crate) enum WorkItem<'h, INFO: VisibleInfo> {
+    /// Still to be looked at
+    Visit {
+        data_for_info_set: &'h DataForInfoSet<INFO>,
+        gamestate: INFO::Gamestate,
+        reach: ReachProbabilities,
+        destination: UtilityDestination,
+    },
+    /// Its moves are on the stack above it. Once they're done, their utilities are the ones from
+    /// `utilities_from` on
+    Complete {
+        data_for_info_set: &'h DataForInfoSet<INFO>,
+        reach: ReachProbabilities,
+        destination: UtilityDestination,
+        picked: PickedMoves,
+        utilities_from: usize,
+    },
+}
+
 #[derive(Debug)]
 pub(crate) struct ThreadLocalWorkStack<'h, INFO: VisibleInfo> {
-    stack: Vec<(&'h DataForInfoSet<INFO>, INFO::Gamestate)>,
+    stack: Vec<WorkItem<'h, INFO>>,
+    // Utilities of finished gamestates, waiting for the gamestate before them to be completed
+    utilities: Vec<(UtilityDestination, UtilityForAllPlayers)>,
+    peak_len: usize,
 }
 
 impl<'h, INFO: VisibleInfo> ThreadLocalWorkStack<'h, INFO> {
-    pub(crate) fn push(
-        &mut self,
-        data_for_info_set: &'h DataForInfoSet<INFO>,
-        gamestate: INFO::Gamestate,
-    ) {
-        self.stack.push((data_for_info_set, gamestate));
+    pub(crate) fn push(&mut self, item: WorkItem<'h, INFO>) {
+        self.stack.push(item);
+        self.peak_len = self.peak_len.max(self.stack.len());
     }
 
-    pub(crate) fn pop(&mut self) -> Option<(&'h DataForInfoSet<INFO>, INFO::Gamestate)> {
+    pub(crate) fn pop(&mut self) -> Option<WorkItem<'h, INFO>> {
         self.stack.pop()
     }
 
@@ -90,9 +187,41 @@ impl<'h, INFO: VisibleInfo> ThreadLocalWorkStack<'h, INFO> {
         self.stack.len()
     }
 
+    pub(crate) fn push_utility(
+        &mut self,
+        destination: UtilityDestination,
+        utility: UtilityForAllPlayers,
+    ) {
+        self.utilities.push((destination, utility));
+    }
+
+    /// Where the utilities of moves pushed from now on will start
+    pub(crate) fn utilities_len(&self) -> usize {
+        self.utilities.len()
+    }
+
+    pub(crate) fn take_utilities_from(
+        &mut self,
+        from: usize,
+    ) -> impl Iterator<Item = (UtilityDestination, UtilityForAllPlayers)> + '_ {
+        self.utilities.drain(from..)
+    }
+
+    /// Forgets the utilities of the gamestates traversals started from
+    pub(crate) fn clear_utilities(&mut self) {
+        self.utilities.clear();
+    }
+
+    /// The longest the stack got since this was last called
+    pub(crate) fn take_peak_len(&mut self) -> usize {
+        std::mem::replace(&mut self.peak_len, self.stack.len())
+    }
+
     pub(crate) fn print_debug(&self) {
-        for (_, g) in self.stack.iter() {
-            println!("\t{:?}", g)
+        for item in self.stack.iter() {
+            if let WorkItem::Visit { gamestate, .. } = item {
+                println!("\t{:?}", gamestate)
+            }
         }
     }
 }
@@ -101,6 +230,8 @@ impl<'h, INFO: VisibleInfo> Default for ThreadLocalWorkStack<'h, INFO> {
     fn default() -> Self {
         Self {
             stack: Default::default(),
+            utilities: Default::default(),
+            peak_len: 0,
         }
     }
 }
@@ -0,0 +1,96 @@
+use crate::cfr::game_model::{PlayerNumber, Probability, MAX_PLAYERS};
+
+/// How likely a gamestate on the workstack is to be reached, split up by the player responsible
+/// for each move along the way, and how many moves it took
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ReachProbabilities {
+    players: [Probability; MAX_PLAYERS],
+    // Probability of the world the traversal started from, as given by the sampler
+    chance: Probability,
+    // Probability that the traversal itself chose this path. Moves that are sampled rather than
+    // expanded are already reached in proportion to their probability, so we divide this back out
+    sampling: Probability,
+    depth: u32,
+}
+
+impl ReachProbabilities {
+    pub(crate) const fn new(chance: Probability) -> Self {
+        Self {
+            players: [1.0; MAX_PLAYERS],
+            chance,
+            sampling: 1.0,
+            depth: 0,
+        }
+    }
+
+    /// Reach after `player` takes a move, where every move at this point is being expanded
+    pub(crate) fn after_move(&self, player: PlayerNumber, move_probability: Probability) -> Self {
+        let mut res = *self;
+        res.players[player] *= move_probability;
+        res.depth += 1;
+        res
+    }
+
+    /// Reach after chance picks an outcome with `chance_probability`, where every outcome is
+    /// being enumerated
+    pub(crate) fn after_chance(&self, chance_probability: Probability) -> Self {
+        let mut res = *self;
+        res.chance *= chance_probability;
+        res
+    }
+
+    /// Reach after `player` takes a move that was picked with `sampling_probability`
+    pub(crate) fn after_sampled_move(
+        &self,
+        player: PlayerNumber,
+        move_probability: Probability,
+        sampling_probability: Probability,
+    ) -> Self {
+        let mut res = self.after_move(player, move_probability);
+        res.sampling *= sampling_probability;
+        res
+    }
+
+    pub(crate) fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub(crate) fn player(&self, player: PlayerNumber) -> Probability {
+        self.players[player]
+    }
+
+    /// How much `player`'s counterfactual values here count: the reach of everyone else and of
+    /// chance, corrected for how likely the traversal was to get here at all
+    pub(crate) fn counterfactual_weight(&self, player: PlayerNumber) -> Probability {
+        let others: Probability = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(p, _)| *p != player)
+            .map(|(_, r)| r)
+            .product();
+
+        others * self.chance / self.sampling
+    }
+
+    /// The reach of `player`, corrected for how likely the traversal was to get here at all
+    pub(crate) fn sampled_player_reach(&self, player: PlayerNumber) -> Probability {
+        self.players[player] / self.sampling
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::strategy_generation::workspace_data::reach_probabilities::ReachProbabilities;
+
+    #[test]
+    fn counterfactual_weight_ignores_own_reach() {
+        let reach = ReachProbabilities::new(0.5)
+            .after_move(0, 0.25)
+            .after_sampled_move(1, 0.5, 0.5)
+            .after_move(1, 0.8);
+
+        assert_eq!(reach.counterfactual_weight(0), 0.5 * 0.8);
+        assert_eq!(reach.counterfactual_weight(1), 0.5 * 0.25 / 0.5);
+    }
+}
@@ -1,4 +1,9 @@
-pub(crate) const MAX_BATCH_SIZE: usize = 4;
+use fastrand::Rng;
+use rustc_hash::FxHasher;
+use std::hash::{Hash, Hasher};
+
+/// How many worlds get traversed at once on each iteration, unless set otherwise
+pub(crate) const DEFAULT_BATCH_SIZE: usize = 4;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) struct Timestamp {
@@ -8,7 +13,6 @@ pub(crate) struct Timestamp {
 
 impl Timestamp {
     pub fn new(cfr_iteration: u32, item_within_batch: usize) -> Self {
-        debug_assert!(item_within_batch < MAX_BATCH_SIZE);
         debug_assert!(cfr_iteration > 0);
 
         Self {
@@ -16,4 +20,15 @@ impl Timestamp {
             item_within_iteration: item_within_batch,
         }
     }
+
+    /// Randomness for `at` during this batch item. The same seed, timestamp and `at` always give
+    /// the same draws, so coming back to a gamestate later in the traversal picks the same way
+    pub(crate) fn rng(&self, seed: u64, at: &impl Hash) -> Rng {
+        let mut seed_builder = FxHasher::default();
+        Hash::hash(at, &mut seed_builder);
+        Hash::hash(self, &mut seed_builder);
+        seed_builder.write_u64(seed);
+
+        Rng::with_seed(seed_builder.finish())
+    }
 }
@@ -0,0 +1,274 @@
+use crate::cfr::game_model::{GamestateSampler, Probability, VisibleInfo};
+use fastrand::Rng;
+use rustc_hash::FxHashSet;
+
+/// How the worlds traversed on each iteration get drawn from the sampler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldSampling {
+    /// How many worlds to traverse per iteration. Each takes up an item in the batch, so this
+    /// can't be more than the batch size, which is also the default
+    pub worlds_per_iteration: Option<usize>,
+    /// Stop drawing worlds once their probabilities add up to this much, even if that leaves
+    /// the iteration with fewer worlds
+    pub stopping_probability: Option<Probability>,
+    /// Whether the same world can be drawn more than once in an iteration. Without replacement,
+    /// drawing gives up after trying ten times as many worlds as it needs, so samplers with few
+    /// worlds still finish
+    ///
+    /// The importance weights below assume worlds are drawn with replacement. Without it, a
+    /// likely world gets into the iteration more often than its sampling probability says, so
+    /// its weight is too large and the regret updates are biased towards it. Only samplers that
+    /// draw every world equally often come out unbiased, and then only once normalized
+    pub with_replacement: bool,
+    /// Scale the weights of the worlds drawn so they add up to one. Either way, each world's
+    /// regret updates are weighed by its probability over how likely the sampler was to draw it,
+    /// see [`GamestateSampler::sampling_probability`]
+    pub normalize: bool,
+}
+
+impl Default for WorldSampling {
+    fn default() -> Self {
+        Self {
+            worlds_per_iteration: None,
+            stopping_probability: None,
+            with_replacement: true,
+            normalize: false,
+        }
+    }
+}
+
+const DRAWS_PER_WORLD_WITHOUT_REPLACEMENT: usize = 10;
+
+impl WorldSampling {
+    /// Called whenever the world sampling or the batch size is set, so a mismatch is caught
+    /// before any training
+    pub(crate) fn check_batch_size(&self, batch_size: usize) {
+        if let Some(worlds) = self.worlds_per_iteration {
+            assert!(
+                worlds <= batch_size,
+                "Can't traverse {} worlds per iteration with a batch size of {}",
+                worlds,
+                batch_size
+            );
+        }
+    }
+
+    /// Draws the worlds for an iteration, each with the weight its regret updates get
+    pub(crate) fn draw<SAMPLER: GamestateSampler>(
+        &self,
+        mut sampler: SAMPLER,
+        batch_size: usize,
+        rng: &mut Rng,
+    ) -> Vec<(<SAMPLER::Info as VisibleInfo>::Gamestate, Probability)> {
+        let worlds = self.worlds_per_iteration.unwrap_or(batch_size);
+        debug_assert!(worlds <= batch_size);
+
+        let max_draws = if self.with_replacement {
+            worlds
+        } else {
+            worlds * DRAWS_PER_WORLD_WITHOUT_REPLACEMENT
+        };
+
+        let mut drawn = Vec::with_capacity(worlds);
+        let mut seen = FxHashSet::default();
+        let mut total_probability = 0.0;
+        let mut total_weight = 0.0;
+        for _ in 0..max_draws {
+            if drawn.len() == worlds
+                || self
+                    .stopping_probability
+                    .is_some_and(|stopping| total_probability >= stopping)
+            {
+                break;
+            }
+
+            let (gamestate, probability) = sampler.sample(rng);
+            if !self.with_replacement && !seen.insert(gamestate.clone()) {
+                continue;
+            }
+
+            let sampling_probability = sampler.sampling_probability(&gamestate, probability);
+            assert!(
+                sampling_probability > 0.0,
+                "A world that got drawn can't have a sampling probability of {}",
+                sampling_probability
+            );
+            let weight = probability / sampling_probability;
+
+            total_probability += probability;
+            total_weight += weight;
+            drawn.push((gamestate, weight));
+        }
+
+        if self.normalize && total_weight > 0.0 {
+            for (_, weight) in &mut drawn {
+                *weight /= total_weight;
+            }
+        }
+
+        drawn
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cfr::game_model::{
+        GamestateSampler, OracleGamestate, PlayerNumber, Probability, UtilityForAllPlayers,
+        VisibleInfo,
+    };
+    use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
+    use crate::cfr::strategy_generation::world_sampling::WorldSampling;
+    use crate::tic_tac_toe::test::board_with_win_for_x;
+    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use bumpalo_herd::Herd;
+    use fastrand::Rng;
+
+    /// A game that's over before it starts, in one of two equally likely worlds
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct World(u8);
+
+    impl OracleGamestate<World> for World {
+        fn info_for_turn_player(&self) -> World {
+            *self
+        }
+
+        fn info_for_player(&self, _player: PlayerNumber) -> World {
+            *self
+        }
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn advance(&self, _m: &bool) -> Self {
+            unreachable!()
+        }
+    }
+
+    impl VisibleInfo for World {
+        type Move = bool;
+        type Gamestate = World;
+
+        fn players_playing(&self) -> PlayerNumber {
+            2
+        }
+
+        fn turn(&self) -> PlayerNumber {
+            0
+        }
+
+        fn run_for_moves(&self, _f: impl FnMut(bool)) -> Option<UtilityForAllPlayers> {
+            Some(UtilityForAllPlayers::default())
+        }
+    }
+
+    /// Draws the first world three times as often as the second
+    #[derive(Debug, Clone)]
+    struct Loaded(u8);
+
+    impl GamestateSampler for Loaded {
+        type Info = World;
+
+        fn sample(&mut self, _rng: &mut Rng) -> (World, Probability) {
+            let world = World((self.0 == 3) as u8);
+            self.0 = (self.0 + 1) % 4;
+            (world, 0.5)
+        }
+
+        fn sampling_probability(
+            &self,
+            gamestate: &World,
+            _probability: Probability,
+        ) -> Probability {
+            if gamestate.0 == 0 {
+                0.75
+            } else {
+                0.25
+            }
+        }
+    }
+
+    #[test]
+    fn worlds_drawn_more_often_weigh_less() {
+        let world_sampling = WorldSampling {
+            normalize: true,
+            ..Default::default()
+        };
+        let drawn = world_sampling.draw(Loaded(0), 4, &mut Rng::new());
+        assert_eq!(drawn.len(), 4);
+
+        for world in [World(0), World(1)] {
+            let weight: Probability = drawn
+                .iter()
+                .filter(|(drawn, _)| *drawn == world)
+                .map(|(_, weight)| weight)
+                .sum();
+            assert!((weight - 0.5).abs() < 1e-9, "{world:?} weighs {weight}");
+        }
+    }
+
+    #[test]
+    fn world_sampling_options() {
+        let sampler = TicTacToeSampler {
+            board: board_with_win_for_x(),
+        };
+
+        let with_replacement =
+            WorldSampling::default().draw(sampler.clone(), 4, &mut Rng::with_seed(0));
+        assert_eq!(with_replacement.len(), 4);
+
+        let world_sampling = WorldSampling {
+            with_replacement: false,
+            normalize: true,
+            ..Default::default()
+        };
+        let without_replacement = world_sampling.draw(sampler, 4, &mut Rng::with_seed(0));
+        assert_eq!(without_replacement.len(), 1);
+        assert_eq!(without_replacement[0].1, 1.0);
+    }
+
+    #[test]
+    fn fewer_worlds_than_the_batch_size() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd)
+            .with_batch_size(4)
+            .with_world_sampling(WorldSampling {
+                worlds_per_iteration: Some(2),
+                ..Default::default()
+            });
+        let board = board_with_win_for_x();
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board.clone(),
+            },
+            1,
+        );
+
+        let mut visits = 0;
+        strategy_generator
+            .into_strategy()
+            .infosets
+            .for_each(|info, data| {
+                if *info == board {
+                    visits = data.visits();
+                }
+            });
+        assert_eq!(visits, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't traverse 8 worlds per iteration with a batch size of 4")]
+    fn more_worlds_than_the_batch_size_is_rejected_up_front() {
+        let herd = Herd::new();
+        let _ = StrategyGenerator::<TicTacToeBoard>::new(&herd)
+            .with_world_sampling(WorldSampling {
+                worlds_per_iteration: Some(8),
+                ..Default::default()
+            })
+            .with_batch_size(4);
+    }
+}
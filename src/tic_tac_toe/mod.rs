@@ -1,13 +1,17 @@
 use crate::cfr::game_model::{
-    GamestateSampler, OracleGamestate, PlayerNumber, Probability, UtilityForAllPlayers, VisibleInfo,
+    invalid_data, Encode, EnumerableGamestateSampler, GamestateSampler, OracleGamestate,
+    PlayerNumber, Probability, UtilityForAllPlayers, VisibleInfo,
 };
+use fastrand::Rng;
 use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{Read, Write};
 use std::sync::LazyLock;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct TicTacToeBoard {
-    squares: [TicTacToeSquare; 9],
-    turn: Player,
+pub(crate) struct TicTacToeBoard {
+    pub(crate) squares: [TicTacToeSquare; 9],
+    pub(crate) turn: Player,
 }
 
 static THREE_IN_ROW: LazyLock<Vec<[usize; 3]>> = LazyLock::new(|| {
@@ -97,6 +101,10 @@ impl OracleGamestate<TicTacToeBoard> for TicTacToeBoard {
         self.clone()
     }
 
+    fn info_for_player(&self, _player: PlayerNumber) -> TicTacToeBoard {
+        self.clone()
+    }
+
     fn players_playing(&self) -> PlayerNumber {
         2
     }
@@ -199,21 +207,41 @@ impl Default for TicTacToeBoard {
     }
 }
 
+impl Encode for TicTacToeBoard {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.squares.encode(w)?;
+        self.turn.encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            squares: Encode::decode(r)?,
+            turn: Encode::decode(r)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
-struct TicTacToeSampler {
-    board: TicTacToeBoard,
+pub(crate) struct TicTacToeSampler {
+    pub(crate) board: TicTacToeBoard,
 }
 
 impl GamestateSampler for TicTacToeSampler {
     type Info = TicTacToeBoard;
 
-    fn sample(&mut self) -> (<Self::Info as VisibleInfo>::Gamestate, Probability) {
+    fn sample(&mut self, _rng: &mut Rng) -> (<Self::Info as VisibleInfo>::Gamestate, Probability) {
         (self.board.clone(), 1.0)
     }
 }
 
+impl EnumerableGamestateSampler for TicTacToeSampler {
+    fn enumerate(&self) -> Vec<(<Self::Info as VisibleInfo>::Gamestate, Probability)> {
+        vec![(self.board.clone(), 1.0)]
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
-enum TicTacToeSquare {
+pub(crate) enum TicTacToeSquare {
     X,
     O,
     #[default]
@@ -221,7 +249,7 @@ enum TicTacToeSquare {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Player {
+pub(crate) enum Player {
     X,
     O,
 }
@@ -237,18 +265,122 @@ impl Display for TicTacToeSquare {
     }
 }
 
+impl Encode for TicTacToeSquare {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            TicTacToeSquare::X => 0u8,
+            TicTacToeSquare::O => 1,
+            TicTacToeSquare::Empty => 2,
+        }
+        .encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(TicTacToeSquare::X),
+            1 => Ok(TicTacToeSquare::O),
+            2 => Ok(TicTacToeSquare::Empty),
+            _ => Err(invalid_data("not a tic-tac-toe square")),
+        }
+    }
+}
+
+impl Encode for Player {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            Player::X => false,
+            Player::O => true,
+        }
+        .encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        Ok(match bool::decode(r)? {
+            false => Player::X,
+            true => Player::O,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct TicTacToeMove {
-    state: TicTacToeSquare,
-    square: usize,
+pub(crate) struct TicTacToeMove {
+    pub(crate) state: TicTacToeSquare,
+    pub(crate) square: usize,
+}
+
+impl Encode for TicTacToeMove {
+    fn encode(&self, w: &mut impl Write) -> io::Result<()> {
+        self.state.encode(w)?;
+        (self.square as u8).encode(w)
+    }
+
+    fn decode(r: &mut impl Read) -> io::Result<Self> {
+        let state = TicTacToeSquare::decode(r)?;
+        let square = u8::decode(r)? as usize;
+        if square >= 9 {
+            return Err(invalid_data("not a tic-tac-toe square"));
+        }
+
+        Ok(Self { state, square })
+    }
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use crate::cfr::game_model::OracleGamestate;
+    use crate::cfr::strategy_generation::strategy::Policy;
     use crate::cfr::strategy_generation::strategy_generator::StrategyGenerator;
-    use crate::tic_tac_toe::{TicTacToeBoard, TicTacToeSampler};
+    use crate::tic_tac_toe::{
+        Player, TicTacToeBoard, TicTacToeMove, TicTacToeSampler, TicTacToeSquare,
+    };
     use bumpalo_herd::Herd;
+    use fastrand::Rng;
+
+    /// X to move, and can win on the spot with [`WINNING_MOVE`]
+    pub(crate) fn board_with_win_for_x() -> TicTacToeBoard {
+        let mut board = TicTacToeBoard::default();
+        board.squares[0] = TicTacToeSquare::X;
+        board.squares[1] = TicTacToeSquare::X;
+        board.squares[3] = TicTacToeSquare::O;
+        board.squares[4] = TicTacToeSquare::O;
+        board.turn = Player::X;
+        board
+    }
+
+    pub(crate) const WINNING_MOVE: TicTacToeMove = TicTacToeMove {
+        state: TicTacToeSquare::X,
+        square: 2,
+    };
+
+    /// X in the middle and O in a corner, with X to move. Small enough to traverse in full, but
+    /// with enough left to play that strategies take a while to converge
+    pub(crate) fn board_with_x_in_the_middle() -> TicTacToeBoard {
+        let mut board = TicTacToeBoard::default();
+        board.squares[4] = TicTacToeSquare::X;
+        board.squares[0] = TicTacToeSquare::O;
+        board.turn = Player::X;
+        board
+    }
+
+    #[test]
+    fn training_takes_the_win() {
+        let herd = Herd::new();
+        let strategy_generator = StrategyGenerator::new(&herd);
+        let board = board_with_win_for_x();
+        strategy_generator.refine_strategy(
+            TicTacToeSampler {
+                board: board.clone(),
+            },
+            200,
+        );
+
+        let average = strategy_generator
+            .lookup_strategy_for_info(&board, Policy::Average)
+            .unwrap();
+        let total: f64 = average.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(average.move_probability(&WINNING_MOVE) > 0.9);
+    }
 
     #[test]
     fn play_a_game() {
@@ -266,25 +398,26 @@ mod test {
         //     println!("{:?}", v);
         // }
 
+        let mut rng = Rng::with_seed(strategy_generator.seed());
         let mut board = TicTacToeBoard::default();
         println!("{}", board);
         println!(
             "{:?}",
             strategy_generator
-                .strategy_for_info(board.clone())
+                .strategy_for_info_or_fallback(&board, Policy::Average, &mut rng)
                 .move_probabilities(),
         );
 
         while let Some(m) = strategy_generator
-            .strategy_for_info(board.clone())
-            .pick_move()
+            .strategy_for_info_or_fallback(&board, Policy::Average, &mut rng)
+            .pick_move(&mut rng)
         {
             board = board.advance(&m);
             println!("{}", board);
             println!(
                 "{:?}",
                 strategy_generator
-                    .strategy_for_info(board.clone())
+                    .strategy_for_info_or_fallback(&board, Policy::Average, &mut rng)
                     .move_probabilities()
             );
         }